@@ -1,11 +1,10 @@
 //! Bitcoin address representation.
 
-use openssl;
-use openssl::crypto::hash::HashType::{SHA256, RIPEMD160};
-
 use util::wif;
+use util::hash::Hash160;
 use protocol::public_key::PublicKey;
 use protocol::private_key::PrivateKey;
+use protocol::network::Network;
 
 // Length of a raw Bitcoin address.
 static LENGTH: uint = 25;
@@ -15,6 +14,23 @@ static LENGTH: uint = 25;
 // with a one.)
 static VERSION_BYTE: u8 = 0x00;
 
+// This byte must be at the start of every pay-to-script-hash (P2SH) Bitcoin
+// address. (It maps to a '3' character in base-58.)
+static P2SH_VERSION_BYTE: u8 = 0x05;
+
+// The version bytes used for the same two kinds of addresses on testnet. (They
+// map to 'm' or 'n', and '2' characters in base-58.)
+static TESTNET_VERSION_BYTE: u8 = 0x6f;
+static TESTNET_P2SH_VERSION_BYTE: u8 = 0xc4;
+
+// Script opcodes used in the output scripts that addresses stand for.
+static OP_DUP: u8 = 0x76;
+static OP_HASH160: u8 = 0xa9;
+static OP_EQUAL: u8 = 0x87;
+static OP_EQUALVERIFY: u8 = 0x88;
+static OP_CHECKSIG: u8 = 0xac;
+static OP_PUSH_20: u8 = 0x14;
+
 /// Represents a raw Bitcoin address. The bytes of an address are laid out like
 /// this:
 ///
@@ -27,19 +43,32 @@ pub struct Address(Vec<u8>);
 
 impl Address {
     /// Creates an Address from raw data. Returns None if the data is not a
-    /// valid Bitcoin address.
+    /// valid regular (not P2SH) mainnet Bitcoin address, which is the only
+    /// kind that wallet files contain. Use new_for_network() to accept other
+    /// kinds of addresses.
     pub fn new(data: &[u8]) -> Option<Address> {
-        if Address::is_valid(data) {
+        match Address::new_for_network(data, Network::Mainnet) {
+            Some(ref address) if address.is_p2sh() => None,
+            address => address
+        }
+    }
+
+    /// Creates an Address from raw data, accepting both regular and P2SH
+    /// addresses for the given network. Returns None if the data is not a
+    /// valid address on that network.
+    pub fn new_for_network(data: &[u8], network: Network) -> Option<Address> {
+        if Address::is_valid(data, network) {
             Some(Address(data.to_vec()))
         } else {
             None
         }
     }
 
-    // Checks if the given raw address data is valid.
-    fn is_valid(data: &[u8]) -> bool {
+    // Checks if the given raw address data is a valid address on the network.
+    fn is_valid(data: &[u8], network: Network) -> bool {
+        let (version, p2sh_version) = version_bytes(network);
         data.len() == LENGTH &&
-        data[0] == VERSION_BYTE &&
+        (data[0] == version || data[0] == p2sh_version) &&
         wif::check(data)
     }
 
@@ -47,19 +76,34 @@ impl Address {
     pub fn from_public_key(public_key: &PublicKey) -> Address {
         // The meat of a Bitcoin address is a RIPEMD-160 hash of a SHA-256 hash
         // of the public key.
-        let public_key_sha = openssl::crypto::hash::hash(SHA256, public_key.get_data());
-        let public_key_ripemd = openssl::crypto::hash::hash(RIPEMD160, public_key_sha.as_slice());
+        let hash = Hash160::from_data(public_key.get_data());
 
         // Encode it in WIF format, which puts the version byte in front and a
         // 4-byte checksum at the end.
-        let data = wif::encode(public_key_ripemd.as_slice(), VERSION_BYTE);
+        let data = wif::encode(hash.get_data(), VERSION_BYTE);
 
         Address(data)
     }
 
-    /// Creates an Address from a PrivateKey.
+    /// Creates a pay-to-script-hash (P2SH) Address on the given network from
+    /// a raw redeem script. This works for any redeem script (multisig, CLTV,
+    /// HTLC, ...), since the address only commits to a hash of the script.
+    pub fn from_redeem_script(script: &[u8], network: Network) -> Address {
+        // Same as with public keys, the address contains a RIPEMD-160 hash of
+        // a SHA-256 hash of the script.
+        let hash = Hash160::from_data(script);
+
+        // P2SH addresses use their own version byte.
+        let (_, p2sh_version) = version_bytes(network);
+        let data = wif::encode(hash.get_data(), p2sh_version);
+
+        Address(data)
+    }
+
+    /// Creates an Address from a PrivateKey, using its uncompressed public
+    /// key.
     pub fn from_private_key(private_key: &PrivateKey) -> Address {
-        let public_key = PublicKey::from_private_key(private_key);
+        let public_key = private_key.to_public_key_uncompressed();
         Address::from_public_key(&public_key)
     }
 
@@ -68,6 +112,79 @@ impl Address {
         let Address(ref data) = *self;
         data.as_slice()
     }
+
+    /// Gets the 20-byte hash that the address is made of, i.e. the address
+    /// without its version byte and checksum.
+    pub fn hash160(&self) -> Hash160 {
+        Hash160::from_bytes(self.get_data().slice(1, 21)).unwrap()
+    }
+
+    /// Whether this is a pay-to-script-hash (P2SH) address.
+    pub fn is_p2sh(&self) -> bool {
+        let version = self.get_data()[0];
+        version == P2SH_VERSION_BYTE || version == TESTNET_P2SH_VERSION_BYTE
+    }
+
+    /// Gets the network that the address is for, based on its version byte.
+    pub fn network(&self) -> Network {
+        let version = self.get_data()[0];
+        if version == TESTNET_VERSION_BYTE || version == TESTNET_P2SH_VERSION_BYTE {
+            Network::Testnet
+        } else {
+            Network::Mainnet
+        }
+    }
+
+    /// Converts the address to the equivalent address on another network,
+    /// i.e. one with the same hash and kind (P2PKH or P2SH) but the other
+    /// network's version byte.
+    pub fn convert_network(&self, to: Network) -> Address {
+        let (version, p2sh_version) = version_bytes(to);
+        let version = if self.is_p2sh() { p2sh_version } else { version };
+
+        Address(wif::encode(self.hash160().get_data(), version))
+    }
+
+    /// Builds the raw output script (scriptPubKey) that pays to this address.
+    /// For a regular address this is:
+    ///
+    ///     OP_DUP OP_HASH160 <hash> OP_EQUALVERIFY OP_CHECKSIG
+    ///
+    /// And for a P2SH address:
+    ///
+    ///     OP_HASH160 <hash> OP_EQUAL
+    pub fn script_pubkey(&self) -> Vec<u8> {
+        let hash = self.hash160();
+        let mut script = Vec::new();
+        if self.is_p2sh() {
+            script.push(OP_HASH160);
+            script.push(OP_PUSH_20);
+            script.push_all(hash.get_data());
+            script.push(OP_EQUAL);
+        } else {
+            script.push(OP_DUP);
+            script.push(OP_HASH160);
+            script.push(OP_PUSH_20);
+            script.push_all(hash.get_data());
+            script.push(OP_EQUALVERIFY);
+            script.push(OP_CHECKSIG);
+        }
+        script
+    }
+
+    /// Checks that the given raw output script is exactly the one that pays to
+    /// this address, e.g. when validating a payment we've received.
+    pub fn matches_script_pubkey(&self, script: &[u8]) -> bool {
+        self.script_pubkey().as_slice() == script
+    }
+}
+
+// Gets the version bytes of regular and P2SH addresses on the given network.
+fn version_bytes(network: Network) -> (u8, u8) {
+    match network {
+        Network::Mainnet => (VERSION_BYTE, P2SH_VERSION_BYTE),
+        Network::Testnet => (TESTNET_VERSION_BYTE, TESTNET_P2SH_VERSION_BYTE)
+    }
 }
 
 #[cfg(test)]
@@ -77,6 +194,7 @@ mod tests {
     use util::base58;
     use protocol::public_key::PublicKey;
     use protocol::private_key::PrivateKey;
+    use protocol::network::Network;
 
     use super::Address;
 
@@ -95,6 +213,93 @@ mod tests {
         assert!(address.is_none());
     }
 
+    #[test]
+    fn test_new_rejects_other_kinds() {
+        let data = base58::decode("35Sbn6ot21sREMtY66QgCCpYVgt4yHtRJy").unwrap();
+        assert!(Address::new(data.as_slice()).is_none());
+
+        let data = base58::decode("mpCHNUuzidXEeveWJv5ko95LYoE2cJ8Pnh").unwrap();
+        assert!(Address::new(data.as_slice()).is_none());
+    }
+
+    #[test]
+    fn test_new_for_network() {
+        let data = base58::decode("19gL5Rq1uc5yspAtbM7NyDs1godKnGHMar").unwrap();
+        assert!(Address::new_for_network(data.as_slice(), Network::Mainnet).is_some());
+        assert!(Address::new_for_network(data.as_slice(), Network::Testnet).is_none());
+
+        let data = base58::decode("mpCHNUuzidXEeveWJv5ko95LYoE2cJ8Pnh").unwrap();
+        assert!(Address::new_for_network(data.as_slice(), Network::Testnet).is_some());
+        assert!(Address::new_for_network(data.as_slice(), Network::Mainnet).is_none());
+
+        let data = base58::decode("2MvzoqqjudUNmS9X5mE2Yp9ooi36Ep4YjqK").unwrap();
+        let address = Address::new_for_network(data.as_slice(), Network::Testnet);
+        assert!(address.unwrap().is_p2sh());
+    }
+
+    #[test]
+    fn test_convert_network() {
+        let data = base58::decode("19gL5Rq1uc5yspAtbM7NyDs1godKnGHMar").unwrap();
+        let address = Address::new(data.as_slice()).unwrap();
+        assert_eq!(address.network(), Network::Mainnet);
+
+        let testnet = address.convert_network(Network::Testnet);
+        assert_eq!(testnet.network(), Network::Testnet);
+        assert_eq!(testnet.hash160(), address.hash160());
+        assert!(!testnet.is_p2sh());
+        let testnet_base58 = base58::encode(testnet.get_data());
+        assert_eq!(testnet_base58.as_slice(), "mpCHNUuzidXEeveWJv5ko95LYoE2cJ8Pnh");
+
+        assert_eq!(testnet.convert_network(Network::Mainnet), address);
+        assert_eq!(address.convert_network(Network::Mainnet), address);
+    }
+
+    #[test]
+    fn test_convert_network_p2sh() {
+        let data = base58::decode("35Sbn6ot21sREMtY66QgCCpYVgt4yHtRJy").unwrap();
+        let address = Address::new_for_network(data.as_slice(), Network::Mainnet).unwrap();
+
+        let testnet = address.convert_network(Network::Testnet);
+        assert!(testnet.is_p2sh());
+        assert_eq!(testnet.hash160(), address.hash160());
+        let testnet_base58 = base58::encode(testnet.get_data());
+        assert_eq!(testnet_base58.as_slice(), "2MvzoqqjudUNmS9X5mE2Yp9ooi36Ep4YjqK");
+    }
+
+    #[test]
+    fn test_hash160() {
+        let data = base58::decode("19gL5Rq1uc5yspAtbM7NyDs1godKnGHMar").unwrap();
+        let address = Address::new(data.as_slice()).unwrap();
+        assert_eq!(address.hash160().get_data(), data.slice(1, 21));
+    }
+
+    #[test]
+    fn test_matches_script_pubkey() {
+        let data = base58::decode("19gL5Rq1uc5yspAtbM7NyDs1godKnGHMar").unwrap();
+        let address = Address::new(data.as_slice()).unwrap();
+        let script = "76A9145F3127253869F56BD6EF94C00E5198DAB56A1F0388AC".from_hex().unwrap();
+        assert!(!address.is_p2sh());
+        assert!(address.matches_script_pubkey(script.as_slice()));
+
+        // Same hash, but a P2SH script.
+        let script = "A9145F3127253869F56BD6EF94C00E5198DAB56A1F0387".from_hex().unwrap();
+        assert!(!address.matches_script_pubkey(script.as_slice()));
+
+        // A different hash.
+        let script = "76A91429268622B4DDA1D2A7923256D0AE9040879F6E7788AC".from_hex().unwrap();
+        assert!(!address.matches_script_pubkey(script.as_slice()));
+    }
+
+    #[test]
+    fn test_matches_script_pubkey_p2sh() {
+        let data = base58::decode("35Sbn6ot21sREMtY66QgCCpYVgt4yHtRJy").unwrap();
+        let address = Address::new_for_network(data.as_slice(), Network::Mainnet).unwrap();
+        let script = "A91429268622B4DDA1D2A7923256D0AE9040879F6E7787".from_hex().unwrap();
+        assert!(address.is_p2sh());
+        assert!(address.matches_script_pubkey(script.as_slice()));
+        assert!(!address.matches_script_pubkey(script.slice_to(22)));
+    }
+
     #[test]
     fn test_from_public_key() {
         let data = "04EB4EA815229359CEC3965507FF68F8B3C7B8632FF9ABD46A06520A838C468AFC\
@@ -106,6 +311,34 @@ mod tests {
         assert_eq!(address_base58.as_slice(), "1BN7qZoGjmpwD3nSLrFy6xfdDQbTvQDUbs");
     }
 
+    #[test]
+    fn test_new_p2sh() {
+        let data = base58::decode("35Sbn6ot21sREMtY66QgCCpYVgt4yHtRJy").unwrap();
+        let address = Address::new_for_network(data.as_slice(), Network::Mainnet);
+        assert!(address.is_some());
+    }
+
+    #[test]
+    fn test_from_redeem_script() {
+        // <500000> OP_CHECKLOCKTIMEVERIFY OP_DROP <pubkey> OP_CHECKSIG
+        let script = "0320A107B1754104EB4EA815229359CEC3965507FF68F8B3C7B8632FF9ABD46A06520A\
+                        838C468AFC5B2EB3588549E626200A698D38966B38498EB27CBAAADE5EEE6DEF01DF061F73AC";
+        let script = script.from_hex().unwrap();
+        let expected_hash = "29268622B4DDA1D2A7923256D0AE9040879F6E77".from_hex().unwrap();
+
+        let address = Address::from_redeem_script(script.as_slice(), Network::Mainnet);
+        assert_eq!(address.hash160().get_data(), expected_hash.as_slice());
+        assert_eq!(address.network(), Network::Mainnet);
+        let address_base58 = base58::encode(address.get_data());
+        assert_eq!(address_base58.as_slice(), "35Sbn6ot21sREMtY66QgCCpYVgt4yHtRJy");
+
+        let address = Address::from_redeem_script(script.as_slice(), Network::Testnet);
+        assert_eq!(address.hash160().get_data(), expected_hash.as_slice());
+        assert_eq!(address.network(), Network::Testnet);
+        let address_base58 = base58::encode(address.get_data());
+        assert_eq!(address_base58.as_slice(), "2MvzoqqjudUNmS9X5mE2Yp9ooi36Ep4YjqK");
+    }
+
     #[test]
     fn test_from_private_key() {
         let data = "F704C5F491F6B1235E6571AD10157A29782A71DF33A8FD7298A50B5CF0A65281";
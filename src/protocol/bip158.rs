@@ -0,0 +1,395 @@
+//! Compact block filters (BIP 158), which let a light client check whether a
+//! block might contain any of its scripts without downloading the block.
+
+use std::u64;
+
+use util::{siphash, varint};
+
+/// The Golomb-Rice parameter of basic filters: each hash's remainder is coded
+/// in this many bits.
+pub static P: uint = 19;
+
+/// The inverse of the false positive rate of basic filters.
+pub static M: u64 = 784931;
+
+// Scripts starting with this opcode are provably unspendable, so they're left
+// out of filters.
+static OP_RETURN: u8 = 0x6a;
+
+/// Builds the basic filter for a block from its scripts, which should be the
+/// output script of every transaction in the block, plus the previous output
+/// script spent by every input (except the coinbase's). The block hash is in
+/// the byte order used on the wire, i.e. reversed from how it's usually
+/// displayed.
+///
+/// Empty scripts, OP_RETURN scripts, and duplicates are left out, as BIP 158
+/// requires. The filter is the number of items as a varint, followed by the
+/// Golomb-Rice coded differences between their sorted hashes.
+pub fn build_filter(block_scripts: &[Vec<u8>], block_hash: &[u8, ..32]) -> Vec<u8> {
+    let mut items: Vec<&[u8]> = block_scripts.iter()
+        .map(|script| script.as_slice())
+        .filter(|script| !script.is_empty() && script[0] != OP_RETURN)
+        .collect();
+    items.sort();
+    items.dedup();
+
+    let n = items.len() as u64;
+    let (k0, k1) = key(block_hash);
+    let mut hashes: Vec<u64> = items.iter()
+        .map(|item| hash_to_range(k0, k1, *item, n * M))
+        .collect();
+    hashes.sort();
+
+    let mut writer = BitWriter::new();
+    let mut last = 0;
+    for &hash in hashes.iter() {
+        writer.write_golomb_rice(hash - last);
+        last = hash;
+    }
+
+    let mut filter = varint::encode(n);
+    filter.push_all(writer.finish().as_slice());
+    filter
+}
+
+/// A basic filter received for a block, which can be checked for scripts.
+#[deriving(Clone, PartialEq, Show)]
+pub struct Filter {
+    n: u64,
+    data: Vec<u8>,
+    key: (u64, u64)
+}
+
+impl Filter {
+    /// Creates a Filter from its serialized form (as returned by
+    /// build_filter()) and the hash of its block, in wire byte order. Returns
+    /// None if the filter doesn't start with a valid varint, or if the number
+    /// of items is so large that the hash range (N * M) would overflow.
+    pub fn new(filter: &[u8], block_hash: &[u8, ..32]) -> Option<Filter> {
+        match varint::decode(filter) {
+            Some((n, length)) if n <= u64::MAX / M => {
+                Some(Filter { n: n, data: filter.slice_from(length).to_vec(), key: key(block_hash) })
+            }
+            _ => None
+        }
+    }
+
+    /// Checks whether the given script might be in the block. False positives
+    /// happen for about 1 in M scripts, but there are no false negatives.
+    pub fn matches(&self, script: &[u8]) -> bool {
+        self.matches_any(&[script.to_vec()])
+    }
+
+    /// Checks whether any of the given scripts might be in the block. This is
+    /// faster than calling matches() on each one, since the filter only has
+    /// to be decoded once.
+    pub fn matches_any(&self, scripts: &[Vec<u8>]) -> bool {
+        let (k0, k1) = self.key;
+        let mut targets: Vec<u64> = scripts.iter()
+            .map(|script| hash_to_range(k0, k1, script.as_slice(), self.n * M))
+            .collect();
+        targets.sort();
+
+        // Walk through the filter's sorted values and the sorted targets
+        // together, looking for one that's in both.
+        let mut reader = BitReader::new(self.data.as_slice());
+        let mut value = 0;
+        let mut targets = targets.iter().peekable();
+        for _ in range(0, self.n) {
+            value += match reader.read_golomb_rice() {
+                Some(delta) => delta,
+                None => return false
+            };
+
+            loop {
+                match targets.peek() {
+                    Some(&&target) if target < value => { targets.next(); }
+                    Some(&&target) if target == value => return true,
+                    Some(_) => break,
+                    None => return false
+                }
+            }
+        }
+
+        false
+    }
+}
+
+// Gets the SipHash key for a block's filter, which is the first 16 bytes of
+// its hash.
+fn key(block_hash: &[u8, ..32]) -> (u64, u64) {
+    (read_le(block_hash.slice(0, 8)), read_le(block_hash.slice(8, 16)))
+}
+
+// Hashes an item and maps the hash uniformly onto the range [0, f).
+fn hash_to_range(k0: u64, k1: u64, item: &[u8], f: u64) -> u64 {
+    multiply_high(siphash::hash(k0, k1, item), f)
+}
+
+// Computes the top 64 bits of the 128-bit product of two numbers, by
+// multiplying their 32-bit halves separately.
+fn multiply_high(a: u64, b: u64) -> u64 {
+    let (a_high, a_low) = (a >> 32, a & 0xffffffff);
+    let (b_high, b_low) = (b >> 32, b & 0xffffffff);
+
+    let low_low = a_low * b_low;
+    let high_low = a_high * b_low;
+    let low_high = a_low * b_high;
+    let high_high = a_high * b_high;
+
+    // This can't overflow: it's at most 2 * (2^32 - 1) + (2^32 - 1)^2.
+    let middle = (low_low >> 32) + (high_low & 0xffffffff) + low_high;
+
+    high_high + (high_low >> 32) + (middle >> 32)
+}
+
+// Reads 8 bytes as a little-endian number.
+fn read_le(bytes: &[u8]) -> u64 {
+    bytes.iter().enumerate().fold(0, |n, (i, &byte)| n | (byte as u64 << (i * 8)))
+}
+
+// Writes a stream of bits, most significant bit of each byte first.
+struct BitWriter {
+    bytes: Vec<u8>,
+    current: u8,
+    bit_count: uint
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter { bytes: Vec::new(), current: 0, bit_count: 0 }
+    }
+
+    fn write_bit(&mut self, bit: bool) {
+        self.current = (self.current << 1) | (bit as u8);
+        self.bit_count += 1;
+        if self.bit_count == 8 {
+            self.bytes.push(self.current);
+            self.current = 0;
+            self.bit_count = 0;
+        }
+    }
+
+    // Writes the lowest `count` bits of the value, most significant first.
+    fn write_bits(&mut self, value: u64, count: uint) {
+        for i in range(0, count).rev() {
+            self.write_bit((value >> i) & 1 == 1);
+        }
+    }
+
+    // Writes the value Golomb-Rice coded with parameter P: the quotient
+    // `value >> P` in unary (that many ones and then a zero), followed by the
+    // low P bits.
+    fn write_golomb_rice(&mut self, value: u64) {
+        for _ in range(0, value >> P) {
+            self.write_bit(true);
+        }
+        self.write_bit(false);
+        self.write_bits(value, P);
+    }
+
+    // Pads the last byte with zero bits, and returns all of the bytes.
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            let padding = 8 - self.bit_count;
+            self.bytes.push(self.current << padding);
+        }
+        self.bytes
+    }
+}
+
+// Reads a stream of bits written by a BitWriter.
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    position: uint
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8]) -> BitReader<'a> {
+        BitReader { bytes: bytes, position: 0 }
+    }
+
+    fn read_bit(&mut self) -> Option<bool> {
+        if self.position >= self.bytes.len() * 8 {
+            return None;
+        }
+
+        let byte = self.bytes[self.position / 8];
+        let bit = (byte >> (7 - self.position % 8)) & 1 == 1;
+        self.position += 1;
+        Some(bit)
+    }
+
+    // Reads a Golomb-Rice coded value, as written by
+    // BitWriter::write_golomb_rice().
+    fn read_golomb_rice(&mut self) -> Option<u64> {
+        let mut quotient = 0u64;
+        loop {
+            match self.read_bit() {
+                Some(true) => quotient += 1,
+                Some(false) => break,
+                None => return None
+            }
+        }
+
+        let mut remainder = 0u64;
+        for _ in range(0, P) {
+            match self.read_bit() {
+                Some(bit) => remainder = (remainder << 1) | (bit as u64),
+                None => return None
+            }
+        }
+
+        Some((quotient << P) | remainder)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serialize::hex::FromHex;
+    use std::u64;
+
+    use util::varint;
+
+    use super::{build_filter, multiply_high, Filter, BitWriter, BitReader, M};
+
+    // The hash of the testnet genesis block, in wire byte order.
+    static GENESIS_HASH: [u8, ..32] =
+        [0x43,0x49,0x7f,0xd7,0xf8,0x26,0x95,0x71,0x08,0xf4,0xa3,0x0f,0xd9,0xce,0xc3,0xae,
+         0xba,0x79,0x97,0x20,0x84,0xe9,0x0e,0xad,0x01,0xea,0x33,0x09,0x00,0x00,0x00,0x00];
+
+    // The output script of the genesis block's coinbase.
+    static GENESIS_SCRIPT: &'static str =
+        "4104678AFDB0FE5548271967F1A67130B7105CD6A828E03909A67962E0EA1F61DE\
+         B649F6BC3F4CEF38C4F35504E51EC112DE5C384DF7BA0B8D578A4C702B6BF11D5FAC";
+
+    #[test]
+    fn test_build_filter_genesis() {
+        // From the BIP 158 test vectors, which use testnet blocks.
+        let scripts = vec![GENESIS_SCRIPT.from_hex().unwrap()];
+        let filter = build_filter(scripts.as_slice(), &GENESIS_HASH);
+        assert_eq!(filter, "019DFCA8".from_hex().unwrap());
+    }
+
+    #[test]
+    fn test_build_filter_skipped_scripts() {
+        let scripts = vec![
+            GENESIS_SCRIPT.from_hex().unwrap(),
+            "76A9145F3127253869F56BD6EF94C00E5198DAB56A1F0388AC".from_hex().unwrap(),
+            "6A0401020304".from_hex().unwrap(),
+            "A91429268622B4DDA1D2A7923256D0AE9040879F6E7787".from_hex().unwrap(),
+            GENESIS_SCRIPT.from_hex().unwrap(),
+            Vec::new()
+        ];
+        let filter = build_filter(scripts.as_slice(), &GENESIS_HASH);
+        assert_eq!(filter, "03C64AF2CC6E06912A".from_hex().unwrap());
+    }
+
+    #[test]
+    fn test_build_filter_several_scripts() {
+        // One script of each common kind: P2PK, P2PKH, P2SH, P2WPKH, P2WSH,
+        // and P2TR. Their hash differences take 0 to 3 unary bits, so this
+        // exercises the quotients as well as the remainders. The expected
+        // filter was computed with a separate implementation of BIP 158,
+        // which also gives the official genesis filter above.
+        let scripts = vec![
+            GENESIS_SCRIPT.from_hex().unwrap(),
+            "76A9145F3127253869F56BD6EF94C00E5198DAB56A1F0388AC".from_hex().unwrap(),
+            "A91429268622B4DDA1D2A7923256D0AE9040879F6E7787".from_hex().unwrap(),
+            "0014751E76E8199196D454941C45D1B3A323F1433BD6".from_hex().unwrap(),
+            "00201863143C14C5166804BD19203356DA136C985678CD4D27A1B8C6329604903262".from_hex().unwrap(),
+            "5120A60869F0DBCF1DC659C9CECBAF8050135EA9E8CDC487053F1DC6880949DC684C".from_hex().unwrap(),
+            "76A91462E907B15CBF27D5425399EBF6F0FB50EBB88F1888AC".from_hex().unwrap()
+        ];
+        let filter = build_filter(scripts.as_slice(), &GENESIS_HASH);
+        assert_eq!(filter, "07ED4BBF25B2ECF815EB64B02A3C06CAEC5E40C0".from_hex().unwrap());
+
+        let filter = Filter::new(filter.as_slice(), &GENESIS_HASH).unwrap();
+        for script in scripts.iter() {
+            assert!(filter.matches(script.as_slice()));
+        }
+    }
+
+    #[test]
+    fn test_build_filter_empty() {
+        let filter = build_filter(&[], &GENESIS_HASH);
+        assert_eq!(filter, vec![0x00]);
+    }
+
+    #[test]
+    fn test_filter_matches() {
+        let scripts = vec![
+            GENESIS_SCRIPT.from_hex().unwrap(),
+            "76A9145F3127253869F56BD6EF94C00E5198DAB56A1F0388AC".from_hex().unwrap(),
+            "A91429268622B4DDA1D2A7923256D0AE9040879F6E7787".from_hex().unwrap()
+        ];
+        let filter = build_filter(scripts.as_slice(), &GENESIS_HASH);
+        let filter = Filter::new(filter.as_slice(), &GENESIS_HASH).unwrap();
+
+        for script in scripts.iter() {
+            assert!(filter.matches(script.as_slice()));
+        }
+
+        // Left out of the filter, and not false positives for this block.
+        let other = "76A914000000000000000000000000000000000000000088AC".from_hex().unwrap();
+        assert!(!filter.matches(other.as_slice()));
+        assert!(!filter.matches("6A0401020304".from_hex().unwrap().as_slice()));
+        assert!(!filter.matches(&[0x51]));
+    }
+
+    #[test]
+    fn test_filter_matches_any() {
+        let filter = Filter::new("019DFCA8".from_hex().unwrap().as_slice(), &GENESIS_HASH).unwrap();
+        let genesis_script = GENESIS_SCRIPT.from_hex().unwrap();
+        let other = "76A914000000000000000000000000000000000000000088AC".from_hex().unwrap();
+
+        assert!(filter.matches_any(&[other.clone(), genesis_script.clone()]));
+        assert!(filter.matches_any(&[genesis_script]));
+        assert!(!filter.matches_any(&[other, vec![0x51]]));
+        assert!(!filter.matches_any(&[]));
+    }
+
+    #[test]
+    fn test_filter_empty() {
+        let filter = Filter::new(&[0x00], &GENESIS_HASH).unwrap();
+        assert!(!filter.matches(GENESIS_SCRIPT.from_hex().unwrap().as_slice()));
+
+        assert!(Filter::new(&[], &GENESIS_HASH).is_none());
+    }
+
+    #[test]
+    fn test_filter_too_many_items() {
+        // The largest N whose hash range fits in 64 bits is accepted.
+        let filter = varint::encode(u64::MAX / M);
+        assert!(Filter::new(filter.as_slice(), &GENESIS_HASH).is_some());
+
+        // Any more would overflow N * M.
+        let filter = varint::encode(u64::MAX / M + 1);
+        assert_eq!(Filter::new(filter.as_slice(), &GENESIS_HASH), None);
+        let filter = varint::encode(u64::MAX);
+        assert_eq!(Filter::new(filter.as_slice(), &GENESIS_HASH), None);
+    }
+
+    #[test]
+    fn test_multiply_high() {
+        assert_eq!(multiply_high(1 << 32, 1 << 32), 1);
+        assert_eq!(multiply_high(0xffffffffffffffff, 0xffffffffffffffff), 0xfffffffffffffffe);
+        assert_eq!(multiply_high(0x8000000000000000, 784931), 392465);
+    }
+
+    #[test]
+    fn test_write_golomb_rice() {
+        let mut writer = BitWriter::new();
+        writer.write_golomb_rice((2 << 19) | 5);
+        // 110, then 5 in 19 bits, then padding.
+        assert_eq!(writer.finish(), vec![0xc0, 0x00, 0x14]);
+    }
+
+    #[test]
+    fn test_read_golomb_rice() {
+        let bytes = [0xc0, 0x00, 0x14];
+        let mut reader = BitReader::new(&bytes);
+        assert_eq!(reader.read_golomb_rice(), Some((2 << 19) | 5));
+        assert_eq!(reader.read_golomb_rice(), None);
+    }
+}
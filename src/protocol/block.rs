@@ -0,0 +1,132 @@
+//! Functions for working with the contents of blocks.
+
+use util::wif;
+
+/// The subsidy paid to the miner of the genesis block, in satoshis (50 BTC).
+pub static INITIAL_SUBSIDY: u64 = 50 * 100_000_000;
+
+/// The number of blocks between each halving of the subsidy.
+pub static HALVING_INTERVAL: u32 = 210_000;
+
+/// Computes the merkle root of the given transaction hashes, in the order they
+/// appear in the block. When a level has an odd number of hashes, the last one
+/// is paired with itself. Returns all zeroes if there are no hashes.
+pub fn merkle_root(hashes: &[[u8, ..32]]) -> [u8, ..32] {
+    if hashes.is_empty() {
+        return [0u8, ..32];
+    }
+
+    let mut level = hashes.to_vec();
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            let last = level[level.len() - 1];
+            level.push(last);
+        }
+
+        level = level.as_slice().chunks(2).map(|pair| {
+            let mut data = pair[0].to_vec();
+            data.push_all(pair[1].as_slice());
+            to_hash(wif::double_sha256(data.as_slice()).as_slice())
+        }).collect();
+    }
+
+    level[0]
+}
+
+/// Computes the witness commitment that goes in a block's coinbase (BIP 141).
+/// This is the double SHA-256 of the merkle root of the wtxids, followed by
+/// the witness reserved value. The first wtxid is the coinbase's, which is
+/// always taken to be all zeroes, whatever is passed in.
+pub fn witness_commitment(wtxids: &[[u8, ..32]], witness_reserved: &[u8, ..32]) -> [u8, ..32] {
+    let mut hashes = wtxids.to_vec();
+    if !hashes.is_empty() {
+        hashes[0] = [0u8, ..32];
+    }
+
+    let mut data = merkle_root(hashes.as_slice()).to_vec();
+    data.push_all(witness_reserved.as_slice());
+    to_hash(wif::double_sha256(data.as_slice()).as_slice())
+}
+
+/// Computes the subsidy (the newly created coins, not counting fees) that the
+/// coinbase of the block at the given height may claim, in satoshis. It starts
+/// at 50 BTC and halves every HALVING_INTERVAL blocks, until it reaches zero.
+pub fn subsidy(height: u32) -> u64 {
+    let halvings = height / HALVING_INTERVAL;
+
+    // Shifting a u64 by 64 or more bits is undefined, and the subsidy has
+    // long since rounded down to zero by then anyway.
+    if halvings >= 64 {
+        return 0;
+    }
+
+    INITIAL_SUBSIDY >> halvings as uint
+}
+
+// Copies a 32-byte hash into an array.
+fn to_hash(bytes: &[u8]) -> [u8, ..32] {
+    let mut hash = [0u8, ..32];
+    for (dest, src) in hash.iter_mut().zip(bytes.iter()) {
+        *dest = *src;
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use std::u32;
+    use serialize::hex::FromHex;
+
+    use super::{merkle_root, witness_commitment, subsidy, to_hash};
+
+    #[test]
+    fn test_merkle_root() {
+        let hashes = [[0x00, ..32], [0x11, ..32], [0x22, ..32]];
+        let expected = "16c94d996b5f836f454a3e1f9be1df8ba65d708d54b8ae84f52122b9c856e928";
+        assert_eq!(merkle_root(&hashes).to_vec(), expected.from_hex().unwrap());
+
+        assert_eq!(merkle_root(&[[0x11, ..32]]), [0x11, ..32]);
+    }
+
+    #[test]
+    fn test_witness_commitment_coinbase_only() {
+        // The commitment found in every block whose only transaction is the
+        // coinbase, with the usual all-zero reserved value.
+        let expected = "e2f61c3f71d1defd3fa999dfa36953755c690689799962b48bebd836974e8cf9";
+        let commitment = witness_commitment(&[[0xff, ..32]], &[0x00, ..32]);
+        assert_eq!(commitment.to_vec(), expected.from_hex().unwrap());
+    }
+
+    #[test]
+    fn test_witness_commitment() {
+        let wtxids = [[0x00, ..32], [0x11, ..32], [0x22, ..32]];
+
+        let expected = "3b0b28a35a5db3050094cbe23b6387c5ff071b6aa07ca8ea5630b2f0737e275a";
+        let commitment = witness_commitment(&wtxids, &[0x00, ..32]);
+        assert_eq!(commitment.to_vec(), expected.from_hex().unwrap());
+
+        let expected = "dbb7617a6a2be8fa731afeb19591f66dd718aed58a2a9cf7b0f0c5e52c5456d1";
+        let commitment = witness_commitment(&wtxids, &[0x01, ..32]);
+        assert_eq!(commitment.to_vec(), expected.from_hex().unwrap());
+    }
+
+    #[test]
+    fn test_subsidy() {
+        assert_eq!(subsidy(0), 5_000_000_000);
+        assert_eq!(subsidy(209_999), 5_000_000_000);
+        assert_eq!(subsidy(210_000), 2_500_000_000);
+        assert_eq!(subsidy(420_000), 1_250_000_000);
+
+        // 50 BTC is less than 2^33 satoshis, so it hits zero after 33 halvings.
+        assert_eq!(subsidy(33 * 210_000 - 1), 1);
+        assert_eq!(subsidy(33 * 210_000), 0);
+        assert_eq!(subsidy(64 * 210_000), 0);
+        assert_eq!(subsidy(u32::MAX), 0);
+    }
+
+    #[test]
+    fn test_to_hash() {
+        let bytes = Vec::from_elem(32, 0xab);
+        assert_eq!(to_hash(bytes.as_slice()), [0xab, ..32]);
+    }
+}
@@ -3,4 +3,9 @@
 pub mod private_key;
 pub mod public_key;
 pub mod address;
-
+pub mod stealth;
+pub mod network;
+pub mod net;
+pub mod block;
+pub mod bip158;
+pub mod taproot;
@@ -0,0 +1,659 @@
+//! Bitcoin P2P network messages.
+
+use util::{wif, varint, varstring};
+use protocol::network::Network;
+
+/// The length of a serialized message header.
+pub static HEADER_LENGTH: uint = 24;
+
+/// The largest payload length we accept in a message header (32 MiB, the
+/// same limit Bitcoin Core uses).
+pub static MAX_PAYLOAD_LENGTH: u32 = 0x02000000;
+
+// The length of the null-padded command name in a message header.
+static COMMAND_LENGTH: uint = 12;
+
+/// The length of a serialized NetworkAddress.
+pub static NETWORK_ADDRESS_LENGTH: uint = 26;
+
+/// The inventory type for a transaction.
+pub static MSG_TX: u32 = 1;
+
+/// The inventory type for a block.
+pub static MSG_BLOCK: u32 = 2;
+
+// The length of a serialized inventory entry: a 4-byte type and a 32-byte
+// hash.
+static INV_ENTRY_LENGTH: uint = 36;
+
+/// The ways parsing a P2P message can fail.
+#[deriving(Clone, PartialEq, Show)]
+pub enum Error {
+    /// There are fewer bytes than the message needs.
+    TooShort,
+    /// There are more bytes than the header says the payload has.
+    TooLong,
+    /// The magic bytes are for a different network than expected.
+    WrongMagic,
+    /// The command isn't printable ASCII padded with null bytes.
+    InvalidCommand,
+    /// The stated payload length is larger than MAX_PAYLOAD_LENGTH.
+    PayloadTooLarge,
+    /// The payload doesn't match the checksum in the header.
+    InvalidChecksum,
+    /// A var-string in the payload is cut off or isn't valid UTF-8.
+    InvalidString
+}
+
+/// Represents the header that comes before the payload of every P2P message.
+/// The bytes of a serialized header are laid out like this:
+///
+///     mmmmccccccccccccllllssss
+///
+/// Where `m` is the network's magic bytes, `c` is the ASCII command name
+/// padded with null bytes, `l` is the little-endian length of the payload, and
+/// `s` is the payload's checksum.
+///
+/// The command is private so that it always fits in its 12 bytes; use
+/// command() to read it.
+#[deriving(Clone, PartialEq, Show)]
+pub struct MessageHeader {
+    pub magic: [u8, ..4],
+    command: String,
+    pub length: u32,
+    pub checksum: [u8, ..4]
+}
+
+impl MessageHeader {
+    /// Creates the header for a message with the given command and payload on
+    /// the given network. Returns None if the command isn't at most 12
+    /// printable ASCII characters, since parse() would reject it.
+    pub fn new(network: Network, command: &str, payload: &[u8]) -> Option<MessageHeader> {
+        if command.len() > COMMAND_LENGTH || !is_valid_command(command.as_bytes()) {
+            return None;
+        }
+
+        Some(MessageHeader {
+            magic: network.magic(),
+            command: command.to_string(),
+            length: payload.len() as u32,
+            checksum: message_checksum(payload)
+        })
+    }
+
+    /// Gets the command name, without the null padding.
+    pub fn command(&self) -> &str {
+        self.command.as_slice()
+    }
+
+    /// Parses a header from its 24-byte wire format, making sure it has the
+    /// expected magic bytes, a valid command name, and a reasonable payload
+    /// length. If the payload follows the header in `bytes`, its checksum is
+    /// verified too; if only the header is given, use verify_payload() once
+    /// the payload has been read.
+    pub fn parse(bytes: &[u8], expected_magic: [u8, ..4]) -> Result<MessageHeader, Error> {
+        if bytes.len() < HEADER_LENGTH {
+            return Err(Error::TooShort);
+        }
+
+        if bytes.slice(0, 4) != expected_magic.as_slice() {
+            return Err(Error::WrongMagic);
+        }
+
+        let command = match parse_command(bytes.slice(4, 16)) {
+            Some(command) => command,
+            None => return Err(Error::InvalidCommand)
+        };
+
+        let length = read_le(bytes.slice(16, 20)) as u32;
+        if length > MAX_PAYLOAD_LENGTH {
+            return Err(Error::PayloadTooLarge);
+        }
+
+        let header = MessageHeader {
+            magic: expected_magic,
+            command: command,
+            length: length,
+            checksum: [bytes[20], bytes[21], bytes[22], bytes[23]]
+        };
+
+        if bytes.len() > HEADER_LENGTH {
+            let end = HEADER_LENGTH + length as uint;
+            if bytes.len() < end {
+                return Err(Error::TooShort);
+            }
+            try!(header.verify_payload(bytes.slice(HEADER_LENGTH, end)));
+        }
+
+        Ok(header)
+    }
+
+    /// Checks that the given payload has the length and checksum stated in the
+    /// header.
+    pub fn verify_payload(&self, payload: &[u8]) -> Result<(), Error> {
+        if payload.len() < self.length as uint {
+            return Err(Error::TooShort);
+        } else if payload.len() > self.length as uint {
+            return Err(Error::TooLong);
+        }
+
+        if message_checksum(payload) != self.checksum {
+            return Err(Error::InvalidChecksum);
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the header into its 24-byte wire format.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(HEADER_LENGTH);
+
+        result.push_all(self.magic.as_slice());
+
+        result.push_all(self.command.as_bytes());
+        for _ in range(self.command.len(), COMMAND_LENGTH) {
+            result.push(0);
+        }
+
+        push_le(&mut result, self.length as u64, 4);
+        result.push_all(self.checksum.as_slice());
+
+        result
+    }
+}
+
+/// Computes the checksum of a message payload, which is the first four bytes
+/// of its double SHA-256 hash.
+pub fn message_checksum(payload: &[u8]) -> [u8, ..4] {
+    let checksum = wif::checksum(payload);
+    [checksum[0], checksum[1], checksum[2], checksum[3]]
+}
+
+/// Represents the address of a node on the network. The bytes of a serialized
+/// network address are laid out like this:
+///
+///     ttttssssssssiiiiiiiiiiiiiiiipp
+///
+/// Where `t` is the little-endian timestamp of when the node was last seen,
+/// `s` is the little-endian services bitfield, `i` is the IPv6 address (or
+/// IPv4-mapped IPv6 address), and `p` is the big-endian port. The timestamp is
+/// left out inside version messages, giving 26 bytes instead of 30.
+#[deriving(Clone, PartialEq, Show)]
+pub struct NetworkAddress {
+    pub timestamp: Option<u32>,
+    pub services: u64,
+    pub ip: [u8, ..16],
+    pub port: u16
+}
+
+impl NetworkAddress {
+    /// Creates a NetworkAddress without a timestamp for the given IPv4
+    /// address, which gets stored as an IPv4-mapped IPv6 address
+    /// (::ffff:a.b.c.d).
+    pub fn from_ipv4(services: u64, ipv4: [u8, ..4], port: u16) -> NetworkAddress {
+        let mut ip = [0u8, ..16];
+        ip[10] = 0xff;
+        ip[11] = 0xff;
+        for (idx, &byte) in ipv4.iter().enumerate() {
+            ip[12 + idx] = byte;
+        }
+
+        NetworkAddress { timestamp: None, services: services, ip: ip, port: port }
+    }
+
+    /// Serializes the network address into its wire format, which is 26
+    /// bytes, or 30 bytes if it has a timestamp.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut result = Vec::with_capacity(NETWORK_ADDRESS_LENGTH + 4);
+        match self.timestamp {
+            Some(timestamp) => push_le(&mut result, timestamp as u64, 4),
+            None => {}
+        }
+        push_le(&mut result, self.services, 8);
+        result.push_all(self.ip.as_slice());
+        result.push((self.port >> 8) as u8);
+        result.push(self.port as u8);
+        result
+    }
+
+    /// Parses a network address without a timestamp from its 26-byte wire
+    /// format. Any bytes after the address are ignored.
+    pub fn deserialize(bytes: &[u8]) -> Result<NetworkAddress, Error> {
+        let mut reader = Reader::new(bytes);
+        NetworkAddress::read(&mut reader, None)
+    }
+
+    /// Parses a network address with a timestamp from its 30-byte wire
+    /// format. Any bytes after the address are ignored.
+    pub fn deserialize_with_timestamp(bytes: &[u8]) -> Result<NetworkAddress, Error> {
+        let mut reader = Reader::new(bytes);
+        let timestamp = try!(reader.read_le(4)) as u32;
+        NetworkAddress::read(&mut reader, Some(timestamp))
+    }
+
+    // Reads the rest of a network address from the given Reader, after the
+    // timestamp if there is one.
+    fn read(reader: &mut Reader, timestamp: Option<u32>) -> Result<NetworkAddress, Error> {
+        let services = try!(reader.read_le(8));
+
+        let mut ip = [0u8, ..16];
+        for (idx, &byte) in try!(reader.read(16)).iter().enumerate() {
+            ip[idx] = byte;
+        }
+
+        let port = try!(reader.read(2));
+        let port = (port[0] as u16 << 8) | port[1] as u16;
+
+        Ok(NetworkAddress { timestamp: timestamp, services: services, ip: ip, port: port })
+    }
+}
+
+/// Represents the payload of a version message, which each side sends when a
+/// connection is opened.
+#[deriving(Clone, PartialEq, Show)]
+pub struct VersionMessage {
+    pub version: i32,
+    pub services: u64,
+    pub timestamp: i64,
+    pub addr_recv: NetworkAddress,
+    pub addr_from: NetworkAddress,
+    pub nonce: u64,
+    pub user_agent: String,
+    pub start_height: i32,
+    pub relay: bool
+}
+
+impl VersionMessage {
+    /// Serializes the version message into its wire format.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut result = Vec::new();
+        push_le(&mut result, self.version as u64, 4);
+        push_le(&mut result, self.services, 8);
+        push_le(&mut result, self.timestamp as u64, 8);
+        result.push_all(self.addr_recv.serialize().as_slice());
+        result.push_all(self.addr_from.serialize().as_slice());
+        push_le(&mut result, self.nonce, 8);
+        result.push_all(varstring::encode(self.user_agent.as_slice()).as_slice());
+        push_le(&mut result, self.start_height as u64, 4);
+        result.push(if self.relay { 1 } else { 0 });
+        result
+    }
+
+    /// Parses a version message from its wire format. The relay flag was
+    /// added in protocol version 70001, so it defaults to true when it's
+    /// missing.
+    pub fn deserialize(bytes: &[u8]) -> Result<VersionMessage, Error> {
+        let mut reader = Reader::new(bytes);
+
+        let version = try!(reader.read_le(4)) as u32 as i32;
+        let services = try!(reader.read_le(8));
+        let timestamp = try!(reader.read_le(8)) as i64;
+        let addr_recv = try!(NetworkAddress::read(&mut reader, None));
+        let addr_from = try!(NetworkAddress::read(&mut reader, None));
+        let nonce = try!(reader.read_le(8));
+        let user_agent = try!(reader.read_var_string());
+        let start_height = try!(reader.read_le(4)) as u32 as i32;
+        let relay = if reader.is_empty() { true } else { try!(reader.read(1))[0] != 0 };
+
+        Ok(VersionMessage {
+            version: version,
+            services: services,
+            timestamp: timestamp,
+            addr_recv: addr_recv,
+            addr_from: addr_from,
+            nonce: nonce,
+            user_agent: user_agent,
+            start_height: start_height,
+            relay: relay
+        })
+    }
+}
+
+/// Represents the payload of an inv message, which announces transactions or
+/// blocks, or of a getdata message, which requests them (both have the same
+/// format). Each entry is an inventory type (MSG_TX or MSG_BLOCK) and the hash
+/// of the object, in the order it's sent over the wire.
+#[deriving(Clone, PartialEq, Show)]
+pub struct InvMessage {
+    pub entries: Vec<(u32, [u8, ..32])>
+}
+
+impl InvMessage {
+    /// Serializes the message into its wire format: the number of entries as
+    /// a variable-length integer, followed by the entries.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut result = varint::encode(self.entries.len() as u64);
+        for &(inv_type, ref hash) in self.entries.iter() {
+            push_le(&mut result, inv_type as u64, 4);
+            result.push_all(hash.as_slice());
+        }
+        result
+    }
+
+    /// Parses a message from its wire format.
+    pub fn deserialize(bytes: &[u8]) -> Result<InvMessage, Error> {
+        let mut reader = Reader::new(bytes);
+
+        let count = try!(reader.read_varint());
+        if count > (reader.remaining() / INV_ENTRY_LENGTH) as u64 {
+            return Err(Error::TooShort);
+        }
+
+        let mut entries = Vec::with_capacity(count as uint);
+        for _ in range(0, count) {
+            let inv_type = try!(reader.read_le(4)) as u32;
+            let mut hash = [0u8, ..32];
+            for (idx, &byte) in try!(reader.read(32)).iter().enumerate() {
+                hash[idx] = byte;
+            }
+            entries.push((inv_type, hash));
+        }
+
+        Ok(InvMessage { entries: entries })
+    }
+}
+
+// Reads fields one after another from a byte slice, failing with TooShort if
+// the slice runs out.
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: uint
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes: bytes, position: 0 }
+    }
+
+    // Reads the next n bytes.
+    fn read(&mut self, n: uint) -> Result<&'a [u8], Error> {
+        if self.bytes.len() - self.position < n {
+            return Err(Error::TooShort);
+        }
+
+        let result = self.bytes.slice(self.position, self.position + n);
+        self.position += n;
+        Ok(result)
+    }
+
+    // Reads an n-byte little-endian integer.
+    fn read_le(&mut self, n: uint) -> Result<u64, Error> {
+        let bytes = try!(self.read(n));
+        Ok(read_le(bytes))
+    }
+
+    // Reads a variable-length integer.
+    fn read_varint(&mut self) -> Result<u64, Error> {
+        match varint::decode(self.bytes.slice_from(self.position)) {
+            Some((n, length)) => {
+                self.position += length;
+                Ok(n)
+            }
+            None => Err(Error::TooShort)
+        }
+    }
+
+    // Reads a var-string.
+    fn read_var_string(&mut self) -> Result<String, Error> {
+        match varstring::decode(self.bytes.slice_from(self.position)) {
+            Some((s, length)) => {
+                self.position += length;
+                Ok(s)
+            }
+            None => Err(Error::InvalidString)
+        }
+    }
+
+    // The number of bytes left to read.
+    fn remaining(&self) -> uint {
+        self.bytes.len() - self.position
+    }
+
+    // Whether all of the bytes have been read.
+    fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+}
+
+// Parses a command name, which must be printable ASCII characters followed by
+// nothing but null bytes.
+fn parse_command(bytes: &[u8]) -> Option<String> {
+    let length = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    let (name, padding) = bytes.split_at(length);
+
+    if is_valid_command(name) && padding.iter().all(|&b| b == 0) {
+        Some(name.iter().map(|&b| b as char).collect())
+    } else {
+        None
+    }
+}
+
+// Checks that a command name is made of printable ASCII characters.
+fn is_valid_command(name: &[u8]) -> bool {
+    name.iter().all(|&b| b >= 0x20 && b < 0x7f)
+}
+
+// Reads an integer in little-endian byte order, taking up all of the given
+// bytes (at most 8).
+fn read_le(bytes: &[u8]) -> u64 {
+    bytes.iter().enumerate().fold(0u64, |n, (i, &byte)| n | (byte as u64 << (i * 8)))
+}
+
+// Appends the lowest `length` bytes of an integer in little-endian byte order.
+fn push_le(out: &mut Vec<u8>, n: u64, length: uint) {
+    for i in range(0u, length) {
+        out.push((n >> (i * 8)) as u8);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serialize::hex::FromHex;
+
+    use protocol::network::Network;
+
+    use super::{MessageHeader, NetworkAddress, VersionMessage, InvMessage, Error};
+    use super::{MSG_TX, MSG_BLOCK, message_checksum};
+
+    #[test]
+    fn test_serialize_header() {
+        let payload: &[u8] = &[0x01, 0x02, 0x03];
+        let header = MessageHeader::new(Network::Mainnet, "version", payload).unwrap();
+        assert_eq!(header.checksum, [0x19, 0xc6, 0x19, 0x7e]);
+
+        let expected = "F9BEB4D9\
+                        76657273696F6E0000000000\
+                        03000000\
+                        19C6197E".from_hex().unwrap();
+        assert_eq!(header.serialize(), expected);
+    }
+
+    #[test]
+    fn test_new_header_invalid_command() {
+        let payload: &[u8] = &[0x01, 0x02, 0x03];
+        assert!(MessageHeader::new(Network::Mainnet, "sendaddrv2", payload).is_some());
+        assert!(MessageHeader::new(Network::Mainnet, "toolongcommand", payload).is_none());
+        assert!(MessageHeader::new(Network::Mainnet, "ver\nsion", payload).is_none());
+        assert!(MessageHeader::new(Network::Mainnet, "ver\x00sion", payload).is_none());
+        assert!(MessageHeader::new(Network::Mainnet, "versión", payload).is_none());
+    }
+
+    #[test]
+    fn test_parse_header() {
+        let bytes = "F9BEB4D9\
+                     76657273696F6E0000000000\
+                     03000000\
+                     19C6197E".from_hex().unwrap();
+        let header = MessageHeader::parse(bytes.as_slice(), Network::Mainnet.magic());
+        assert!(header.is_ok());
+
+        let header = header.unwrap();
+        assert_eq!(header.command(), "version");
+        assert_eq!(header.length, 3);
+        assert_eq!(header.checksum, [0x19, 0xc6, 0x19, 0x7e]);
+        assert_eq!(header.serialize(), bytes);
+    }
+
+    #[test]
+    fn test_message_checksum() {
+        let payload: &[u8] = &[0x01, 0x02, 0x03];
+        assert_eq!(message_checksum(payload), [0x19, 0xc6, 0x19, 0x7e]);
+    }
+
+    #[test]
+    fn test_parse_header_with_payload() {
+        let bytes = "F9BEB4D9\
+                     76657273696F6E0000000000\
+                     03000000\
+                     19C6197E\
+                     010203".from_hex().unwrap();
+        let header = MessageHeader::parse(bytes.as_slice(), Network::Mainnet.magic());
+        assert!(header.is_ok());
+    }
+
+    #[test]
+    fn test_parse_header_corrupted_payload() {
+        let bytes = "F9BEB4D9\
+                     76657273696F6E0000000000\
+                     03000000\
+                     19C6197E\
+                     010204".from_hex().unwrap();
+        let header = MessageHeader::parse(bytes.as_slice(), Network::Mainnet.magic());
+        assert_eq!(header, Err(Error::InvalidChecksum));
+
+        let header = MessageHeader::parse(bytes.slice_to(24), Network::Mainnet.magic()).unwrap();
+        assert_eq!(header.verify_payload(bytes.slice_from(24)), Err(Error::InvalidChecksum));
+    }
+
+    #[test]
+    fn test_verify_payload_wrong_length() {
+        let payload: &[u8] = &[0x01, 0x02, 0x03];
+        let header = MessageHeader::new(Network::Mainnet, "version", payload).unwrap();
+        assert_eq!(header.verify_payload(payload), Ok(()));
+        assert_eq!(header.verify_payload(payload.slice_to(2)), Err(Error::TooShort));
+        assert_eq!(header.verify_payload(&[0x01, 0x02, 0x03, 0x04]), Err(Error::TooLong));
+    }
+
+    #[test]
+    fn test_parse_header_wrong_magic() {
+        let bytes = "0B110907\
+                     76657273696F6E0000000000\
+                     03000000\
+                     19C6197E".from_hex().unwrap();
+        let header = MessageHeader::parse(bytes.as_slice(), Network::Mainnet.magic());
+        assert_eq!(header, Err(Error::WrongMagic));
+    }
+
+    #[test]
+    fn test_parse_header_invalid_command() {
+        let bytes = "F9BEB4D9\
+                     76657200696F6E0000000000\
+                     03000000\
+                     19C6197E".from_hex().unwrap();
+        let header = MessageHeader::parse(bytes.as_slice(), Network::Mainnet.magic());
+        assert_eq!(header, Err(Error::InvalidCommand));
+    }
+
+    #[test]
+    fn test_parse_header_too_large() {
+        let bytes = "F9BEB4D9\
+                     76657273696F6E0000000000\
+                     01000002\
+                     19C6197E".from_hex().unwrap();
+        let header = MessageHeader::parse(bytes.as_slice(), Network::Mainnet.magic());
+        assert_eq!(header, Err(Error::PayloadTooLarge));
+    }
+
+    #[test]
+    fn test_parse_header_too_short() {
+        let bytes = "F9BEB4D976657273696F6E00".from_hex().unwrap();
+        let header = MessageHeader::parse(bytes.as_slice(), Network::Mainnet.magic());
+        assert_eq!(header, Err(Error::TooShort));
+    }
+
+    #[test]
+    fn test_serialize_network_address() {
+        let address = NetworkAddress::from_ipv4(1, [127, 0, 0, 1], 8333);
+        let expected = "0100000000000000\
+                        00000000000000000000FFFF7F000001\
+                        208D".from_hex().unwrap();
+        assert_eq!(address.serialize(), expected);
+        assert_eq!(NetworkAddress::deserialize(expected.as_slice()), Ok(address));
+    }
+
+    #[test]
+    fn test_serialize_network_address_with_timestamp() {
+        let mut address = NetworkAddress::from_ipv4(1, [127, 0, 0, 1], 8333);
+        address.timestamp = Some(0x548a4c00);
+        let expected = "004C8A54\
+                        0100000000000000\
+                        00000000000000000000FFFF7F000001\
+                        208D".from_hex().unwrap();
+        assert_eq!(address.serialize(), expected);
+        assert_eq!(NetworkAddress::deserialize_with_timestamp(expected.as_slice()), Ok(address));
+    }
+
+    #[test]
+    fn test_version_message_round_trip() {
+        let message = VersionMessage {
+            version: 70002,
+            services: 1,
+            timestamp: 1418000000,
+            addr_recv: NetworkAddress::from_ipv4(1, [127, 0, 0, 1], 8333),
+            addr_from: NetworkAddress::from_ipv4(1, [127, 0, 0, 1], 18333),
+            nonce: 0x0123456789abcdef,
+            user_agent: "/Cykas:0.0.1/".to_string(),
+            start_height: 332000,
+            relay: false
+        };
+
+        let bytes = message.serialize();
+        assert_eq!(bytes.len(), 80 + 1 + 13 + 4 + 1);
+
+        // The user agent is a var-string: its length as a varint, followed by
+        // the UTF-8 bytes.
+        assert_eq!(bytes[80], 13);
+        assert_eq!(bytes.slice(81, 94), b"/Cykas:0.0.1/".as_slice());
+
+        assert_eq!(VersionMessage::deserialize(bytes.as_slice()), Ok(message));
+    }
+
+    #[test]
+    fn test_version_message_without_relay() {
+        let bytes = "62EA0000010000000000000011B2D05000000000\
+                     010000000000000000000000000000000000FFFF000000000000\
+                     010000000000000000000000000000000000FFFF000000000000\
+                     3B2EB35D8CE617650F2F5361746F7368693A302E372E322FC03E0300".from_hex().unwrap();
+        let message = VersionMessage::deserialize(bytes.as_slice()).unwrap();
+        assert_eq!(message.version, 60002);
+        assert_eq!(message.user_agent.as_slice(), "/Satoshi:0.7.2/");
+        assert_eq!(message.start_height, 212672);
+        assert!(message.relay);
+    }
+
+    #[test]
+    fn test_version_message_too_short() {
+        let bytes = "62EA0000010000000000000011B2D05000000000".from_hex().unwrap();
+        assert_eq!(VersionMessage::deserialize(bytes.as_slice()), Err(Error::TooShort));
+    }
+
+    #[test]
+    fn test_inv_message_round_trip() {
+        let message = InvMessage {
+            entries: vec![(MSG_TX, [0x11u8, ..32]), (MSG_BLOCK, [0x22u8, ..32])]
+        };
+
+        let bytes = message.serialize();
+        assert_eq!(bytes.len(), 1 + 2 * 36);
+        assert_eq!(bytes.slice(0, 5), "0201000000".from_hex().unwrap().as_slice());
+        assert_eq!(bytes.slice(37, 41), "02000000".from_hex().unwrap().as_slice());
+
+        assert_eq!(InvMessage::deserialize(bytes.as_slice()), Ok(message));
+    }
+
+    #[test]
+    fn test_inv_message_too_short() {
+        let bytes = "0201000000".from_hex().unwrap();
+        assert_eq!(InvMessage::deserialize(bytes.as_slice()), Err(Error::TooShort));
+    }
+}
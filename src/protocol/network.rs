@@ -0,0 +1,32 @@
+//! The Bitcoin networks that Cykas knows about.
+
+/// Represents a Bitcoin network.
+#[deriving(Clone, Copy, PartialEq, Show)]
+pub enum Network {
+    /// The main Bitcoin network.
+    Mainnet,
+    /// The public test network (testnet3).
+    Testnet
+}
+
+impl Network {
+    /// Gets the four magic bytes that start every P2P message on the network,
+    /// in the order they're sent over the wire.
+    pub fn magic(&self) -> [u8, ..4] {
+        match *self {
+            Network::Mainnet => [0xf9, 0xbe, 0xb4, 0xd9],
+            Network::Testnet => [0x0b, 0x11, 0x09, 0x07]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Network;
+
+    #[test]
+    fn test_magic() {
+        assert_eq!(Network::Mainnet.magic(), [0xf9, 0xbe, 0xb4, 0xd9]);
+        assert_eq!(Network::Testnet.magic(), [0x0b, 0x11, 0x09, 0x07]);
+    }
+}
@@ -1,8 +1,9 @@
 //! Bitcoin private key representation.
 
-use openssl;
+use serialize::hex::FromHex;
 
-use util::wif;
+use util::{wif, ecdsa, base58};
+use util::hash::Sha256Hash;
 use protocol::public_key::PublicKey;
 use protocol::address::Address;
 
@@ -13,25 +14,47 @@ pub static LENGTH: uint = 32u;
 // Import Format (WIF).
 static VERSION_BYTE: u8 = 0x80;
 
-// Bitcoin keys must be less than or equal to this value, as dictated by the
-// secp256k1 curve it uses.
-static MAX: &'static [u8] = &[
-    0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,
-    0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xfe,
-    0xba,0xae,0xdc,0xe6,0xaf,0x48,0xa0,0x3b,
-    0xbf,0xd2,0x5e,0x8c,0xd0,0x36,0x41,0x40
-];
-
-// Bitcoin keys must be greater than zero.
-static ZERO: &'static [u8] = &[
-    0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,
-    0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,
-    0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,
-    0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00
-];
+// This byte is appended to the key in a WIF private key whose public key
+// should be compressed.
+static COMPRESSED_FLAG: u8 = 0x01;
+
+// Lengths of the mini private keys used by Casascius coins and some paper
+// wallets.
+static MINI_KEY_LENGTHS: &'static [uint] = &[22, 26, 30];
+
+/// The errors that can come up when parsing a private key from text.
+#[deriving(Clone, Copy, PartialEq, Show)]
+pub enum Error {
+    /// The text isn't in any of the formats we know about.
+    UnknownFormat,
+    /// The text is in a known format, but doesn't contain a valid key (e.g.
+    /// the checksum is wrong, or the key is out of range).
+    InvalidKey
+}
+
+/// A private key parsed by PrivateKey::parse_list(), along with whether its
+/// format says the compressed public key should be used.
+#[deriving(Clone, PartialEq, Show)]
+pub struct ParsedKey {
+    pub private_key: PrivateKey,
+    pub compressed: bool
+}
+
+impl ParsedKey {
+    /// Derives the address that the key was meant for: the compressed one if
+    /// the key was marked as compressed, otherwise the uncompressed one.
+    pub fn to_address(&self) -> Address {
+        if self.compressed {
+            self.private_key.to_address_compressed()
+        } else {
+            self.private_key.to_address()
+        }
+    }
+}
 
 /// Represents a raw Bitcoin private key, consisting of 32 bytes of data which
-/// must be greater than `ZERO` and no greater than `MAX`, as defined above.
+/// must be a valid scalar for the secp256k1 curve, i.e. in the range
+/// [1, n - 1] where n is the order of the curve.
 #[deriving(Clone, PartialEq, Show)]
 pub struct PrivateKey(Vec<u8>);
 
@@ -49,22 +72,26 @@ impl PrivateKey {
     /// Generates a random Bitcoin private key securely, using openssl's random
     /// bytes generator.
     pub fn generate() -> PrivateKey {
+        PrivateKey(ecdsa::random_scalar())
+    }
+
+    /// Derives a PrivateKey deterministically from a string, by hashing it
+    /// with SHA-256 (and hashing again in the unlikely case that the hash is
+    /// out of range). This is only meant for reproducible examples and tests:
+    /// anyone who can guess the string can spend from the key.
+    pub fn from_string_seed(seed: &str) -> PrivateKey {
+        let mut hash = Sha256Hash::from_data(seed.as_bytes());
         loop {
-            // Just generate 32 random bytes. The result is almost certainly a
-            // valid private key. Just in case it isn't, keep looping until we
-            // get a valid one.
-            let key = openssl::crypto::rand::rand_bytes(LENGTH);
-            if PrivateKey::is_valid(key.as_slice()) {
-                return PrivateKey(key)
+            match PrivateKey::new(hash.get_data()) {
+                Some(key) => return key,
+                None => hash = Sha256Hash::from_data(hash.get_data())
             }
         }
     }
 
     // Checks if the given private key data is valid.
     fn is_valid(data: &[u8]) -> bool {
-        data.len() == LENGTH &&
-        data.as_slice() > ZERO &&
-        data.as_slice() <= MAX
+        ecdsa::is_valid_scalar(data)
     }
 
     /// Decodes the given Wallet Import Format (WIF) raw data into a
@@ -87,6 +114,77 @@ impl PrivateKey {
         None
     }
 
+    /// Parses a list of private keys, one per line, such as the keys printed on
+    /// a paper wallet. Each line can be in any of these formats, which are
+    /// detected automatically:
+    ///
+    /// - WIF, for either a compressed or an uncompressed public key
+    /// - 64 hex digits
+    /// - a mini private key, e.g. `S6c56bnXQiBjk9mqSYE7ykVQ7NzrRy`
+    ///
+    /// Leading and trailing whitespace is ignored, and so are blank lines.
+    /// Only WIF keys can be marked as compressed; hex and mini keys are taken
+    /// to be for uncompressed public keys, as they traditionally are.
+    pub fn parse_list(text: &str) -> Vec<Result<ParsedKey, Error>> {
+        text.lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| PrivateKey::parse(line))
+            .collect()
+    }
+
+    // Parses a single private key, detecting which format it's in.
+    fn parse(text: &str) -> Result<ParsedKey, Error> {
+        let (private_key, compressed) = try!(PrivateKey::parse_key(text));
+        match private_key {
+            Some(private_key) => Ok(ParsedKey { private_key: private_key, compressed: compressed }),
+            None => Err(Error::InvalidKey)
+        }
+    }
+
+    // Detects which format the text is in and decodes it, returning the key
+    // (None if it's invalid) and whether it's marked as compressed.
+    fn parse_key(text: &str) -> Result<(Option<PrivateKey>, bool), Error> {
+        if text.len() == LENGTH * 2 {
+            match text.from_hex() {
+                Ok(data) => return Ok((PrivateKey::new(data.as_slice()), false)),
+                Err(_) => {}
+            }
+        }
+
+        if text.starts_with("S") && MINI_KEY_LENGTHS.contains(&text.len()) {
+            return Ok((PrivateKey::from_mini_key(text), false));
+        }
+
+        let data = match base58::decode(text) {
+            Some(data) => data,
+            None => return Err(Error::UnknownFormat)
+        };
+        if data.len() < 1 || data[0] != VERSION_BYTE {
+            return Err(Error::UnknownFormat);
+        }
+
+        match wif::decode(data.as_slice(), VERSION_BYTE) {
+            Some(ref key) if key.len() == LENGTH + 1 && key[LENGTH] == COMPRESSED_FLAG => {
+                Ok((PrivateKey::new(key.slice_to(LENGTH)), true))
+            }
+            Some(ref key) => Ok((PrivateKey::new(key.as_slice()), false)),
+            None => Err(Error::InvalidKey)
+        }
+    }
+
+    // Decodes a mini private key. Its typo check is that the SHA-256 hash of
+    // the key with a '?' on the end must start with a zero byte, and the
+    // private key itself is the SHA-256 hash of the mini key.
+    fn from_mini_key(text: &str) -> Option<PrivateKey> {
+        let check = Sha256Hash::from_data(format!("{}?", text).as_bytes());
+        if check.get_data()[0] != 0x00 {
+            return None;
+        }
+
+        PrivateKey::new(Sha256Hash::from_data(text.as_bytes()).get_data())
+    }
+
     /// Gets the raw private key as a slice of bytes.
     pub fn get_data(&self) -> &[u8] {
         let PrivateKey(ref data) = *self;
@@ -99,15 +197,36 @@ impl PrivateKey {
         wif::encode(self.get_data().as_slice(), VERSION_BYTE)
     }
 
-    /// Derives the public key from the given private key.
+    /// Derives the compressed public key from the given private key.
     pub fn to_public_key(&self) -> PublicKey {
-        PublicKey::from_private_key(self)
+        let public_key = ecdsa::derive_public_key(self.get_data());
+        PublicKey::new(ecdsa::compress_public_key(public_key.as_slice()).as_slice()).unwrap()
+    }
+
+    /// Derives the uncompressed public key from the given private key.
+    pub fn to_public_key_uncompressed(&self) -> PublicKey {
+        PublicKey::new(ecdsa::derive_public_key(self.get_data()).as_slice()).unwrap()
+    }
+
+    /// Same as to_public_key(), named after what it actually does: multiplies
+    /// the curve's generator point G by the private key.
+    pub fn multiply_by_g(&self) -> PublicKey {
+        self.to_public_key()
     }
 
-    /// Derives the address from the given private key.
+    /// Derives the address from the given private key, using its uncompressed
+    /// public key (the same as Address::from_private_key()). Note that this
+    /// isn't the address of to_public_key(), which is compressed; for that,
+    /// use to_address_compressed().
     pub fn to_address(&self) -> Address {
         Address::from_private_key(self)
     }
+
+    /// Derives the address of the compressed public key, i.e. the address of
+    /// to_public_key().
+    pub fn to_address_compressed(&self) -> Address {
+        self.to_public_key().to_address()
+    }
 }
 
 #[cfg(test)]
@@ -115,9 +234,10 @@ mod tests {
     use serialize::hex::FromHex;
 
     use util::base58;
+    use protocol::address::Address;
 
-    use super::{LENGTH, ZERO, MAX};
-    use super::PrivateKey;
+    use super::LENGTH;
+    use super::{PrivateKey, ParsedKey, Error};
 
     #[test]
     fn test_new() {
@@ -130,13 +250,21 @@ mod tests {
 
     #[test]
     fn test_new_max() {
-        let private_key = PrivateKey::new(MAX);
+        let data = "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364140";
+        let private_key = PrivateKey::new(data.from_hex().unwrap().as_slice());
         assert!(private_key.is_some());
     }
 
     #[test]
     fn test_new_invalid_zero_key() {
-        let private_key = PrivateKey::new(ZERO);
+        let private_key = PrivateKey::new(Vec::from_elem(32, 0u8).as_slice());
+        assert!(private_key.is_none());
+    }
+
+    #[test]
+    fn test_new_invalid_curve_order() {
+        let data = "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141";
+        let private_key = PrivateKey::new(data.from_hex().unwrap().as_slice());
         assert!(private_key.is_none());
     }
 
@@ -163,6 +291,57 @@ mod tests {
         assert!(private_key.is_none());
     }
 
+    #[test]
+    fn test_parse_list() {
+        let text = "5KPqe3y95higsGQaWN6TQPtv2BQ2X1SqL87AmVAuiz811uCQRYQ\n\
+                    L4BogY6nfkizxs14tc75YG492CtdhSy2KQNE7MLjiK7u4Z52u1hD\n\
+                    \n\
+                    \tCFE1B4C8DDA7EBF5FCACC4086BD9530F1C2201AE5A7D1DEF090D911CF28E5C5F  \n\
+                    S6c56bnXQiBjk9mqSYE7ykVQ7NzrRy\n\
+                    not a key\n";
+        let keys = PrivateKey::parse_list(text);
+        assert_eq!(keys.len(), 5);
+
+        let expected = "CFE1B4C8DDA7EBF5FCACC4086BD9530F1C2201AE5A7D1DEF090D911CF28E5C5F";
+        let expected = PrivateKey::new(expected.from_hex().unwrap().as_slice()).unwrap();
+        assert_eq!(keys[0], Ok(ParsedKey { private_key: expected.clone(), compressed: false }));
+        assert_eq!(keys[1], Ok(ParsedKey { private_key: expected.clone(), compressed: true }));
+        assert_eq!(keys[2], Ok(ParsedKey { private_key: expected, compressed: false }));
+
+        let expected = "4C7A9640C72DC2099F23715D0C8A0D8A35F8906E3CAB61DD3F78B67BF887C9AB";
+        let expected = PrivateKey::new(expected.from_hex().unwrap().as_slice()).unwrap();
+        assert_eq!(keys[3], Ok(ParsedKey { private_key: expected, compressed: false }));
+
+        assert_eq!(keys[4], Err(Error::UnknownFormat));
+    }
+
+    #[test]
+    fn test_parse_list_addresses() {
+        let text = "5KPqe3y95higsGQaWN6TQPtv2BQ2X1SqL87AmVAuiz811uCQRYQ\n\
+                    L4BogY6nfkizxs14tc75YG492CtdhSy2KQNE7MLjiK7u4Z52u1hD";
+        let keys = PrivateKey::parse_list(text);
+
+        let uncompressed = keys[0].as_ref().unwrap();
+        assert!(!uncompressed.compressed);
+        let address = base58::encode(uncompressed.to_address().get_data());
+        assert_eq!(address.as_slice(), "19mBXKFr2ZHCKtot7he4E4ENwoodxhhX9T");
+
+        let compressed = keys[1].as_ref().unwrap();
+        assert!(compressed.compressed);
+        let address = base58::encode(compressed.to_address().get_data());
+        assert_eq!(address.as_slice(), "1D9jKK7azHBzbUsCVHYUyYyc8VNLBYrAar");
+    }
+
+    #[test]
+    fn test_parse_list_invalid_keys() {
+        // Wrong checksum, a zero key, and a mini key that fails its typo check.
+        let text = "5J5gwp44QZSNJbaHS4f5w2Wisrt8bHHdmB7rQetgsH7tghvVPY8\n\
+                    0000000000000000000000000000000000000000000000000000000000000000\n\
+                    S6c56bnXQiBjk9mqSYE7ykVQ7NzrRz";
+        let keys = PrivateKey::parse_list(text);
+        assert_eq!(keys, vec![Err(Error::InvalidKey), Err(Error::InvalidKey), Err(Error::InvalidKey)]);
+    }
+
     #[test]
     fn test_generate() {
         let private_key = PrivateKey::generate();
@@ -172,6 +351,21 @@ mod tests {
         assert!(private_key.get_data() != another_key.get_data());
     }
 
+    #[test]
+    fn test_from_string_seed() {
+        let private_key = PrivateKey::from_string_seed("test");
+        let expected = "9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08";
+        assert_eq!(private_key.get_data(), expected.from_hex().unwrap().as_slice());
+
+        let address = base58::encode(private_key.to_address().get_data());
+        assert_eq!(address.as_slice(), "1HKqKTMpBTZZ8H5zcqYEWYBaaWELrDEXeE");
+        let address = base58::encode(private_key.to_address_compressed().get_data());
+        assert_eq!(address.as_slice(), "19eA3hUfKRt7aZymavdQFXg5EZ6KCVKxr8");
+
+        assert_eq!(PrivateKey::from_string_seed("test"), private_key);
+        assert!(PrivateKey::from_string_seed("test2") != private_key);
+    }
+
     #[test]
     fn test_to_wif() {
         let data = "CFE1B4C8DDA7EBF5FCACC4086BD9530F1C2201AE5A7D1DEF090D911CF28E5C5F";
@@ -188,6 +382,18 @@ mod tests {
         let data = data.from_hex().unwrap();
         let private_key = PrivateKey::new(data.as_slice()).unwrap();
         let public_key = private_key.to_public_key();
+        let expected = "03F9C985FBFD543097E0870B36C98CE627BBC4EAD4668040214D53E96DB341A2A0";
+        let expected = expected.from_hex().unwrap();
+        assert_eq!(public_key.get_data(), expected.as_slice());
+        assert_eq!(private_key.multiply_by_g(), public_key);
+    }
+
+    #[test]
+    fn test_to_public_key_uncompressed() {
+        let data = "F91BCBB19F3A8A03204B70B08DB2950716C565E362912C5B368CC171FF578B9F";
+        let data = data.from_hex().unwrap();
+        let private_key = PrivateKey::new(data.as_slice()).unwrap();
+        let public_key = private_key.to_public_key_uncompressed();
         let expected = "04F9C985FBFD543097E0870B36C98CE627BBC4EAD4668040214D53E96DB341A2A0\
                           A55DBBFF18F4422E90E038BECECA97461C4076FA33408D568154A66AC8FA702F";
         let expected = expected.from_hex().unwrap();
@@ -203,5 +409,17 @@ mod tests {
         let expected = base58::decode("14ydpwhvtVBMjt5NrechP46UKLSY7jYn7q").unwrap();
         assert_eq!(address.get_data(), expected.as_slice());
     }
+
+    #[test]
+    fn test_to_address_compressed() {
+        let data = "CBBEC41B016517C3DA8E2F88BDACB293802CECF1AE2C47A7CB5D4BDA28353B5B";
+        let data = data.from_hex().unwrap();
+        let private_key = PrivateKey::new(data.as_slice()).unwrap();
+        let address = private_key.to_address_compressed();
+        let expected = base58::decode("1FEP5UU23LH7xtmMSacDDSZ2WERjVN3imo").unwrap();
+        assert_eq!(address.get_data(), expected.as_slice());
+        assert_eq!(address, private_key.to_public_key().to_address());
+        assert_eq!(private_key.to_address(), Address::from_private_key(&private_key));
+    }
 }
 
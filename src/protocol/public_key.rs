@@ -4,19 +4,31 @@ use util::ecdsa;
 use protocol::private_key::PrivateKey;
 use protocol::address::Address;
 
-// Length of a raw Bitcoin public key.
+// Length of a raw uncompressed Bitcoin public key.
 static LENGTH: uint = 65u;
 
+// Length of a raw compressed Bitcoin public key.
+static COMPRESSED_LENGTH: uint = 33u;
+
 // Initial byte of a public key, signifying it's in uncompressed format.
 static FORMAT_BYTE: u8 = 0x04;
 
-/// Represents a raw Bitcoin public key. The bytes of a public key are laid out
-/// like this:
+// Initial bytes of a compressed public key, signifying whether the Y
+// coordinate is even or odd.
+static COMPRESSED_EVEN_BYTE: u8 = 0x02;
+static COMPRESSED_ODD_BYTE: u8 = 0x03;
+
+/// Represents a raw Bitcoin public key. The bytes of an uncompressed public key
+/// are laid out like this:
 ///
 ///     cxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyyy
 ///
 /// Where `c` is the byte 0x04 signifying that the public key is in
 /// uncompressed format, and `x` and `y` are the 32-byte X and Y coordinates.
+/// A compressed public key leaves out the Y coordinate, and instead starts
+/// with 0x02 if Y is even or 0x03 if Y is odd:
+///
+///     cxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxxx
 #[deriving(Clone, PartialEq, Show)]
 pub struct PublicKey(Vec<u8>);
 
@@ -31,15 +43,26 @@ impl PublicKey {
         }
     }
 
-    // Checks if the given public key data is valid.
+    // Checks if the given public key data is valid, i.e. is in one of the two
+    // formats and is actually a point on the curve.
     fn is_valid(data: &[u8]) -> bool {
-        data.len() == LENGTH &&
-        data[0] == FORMAT_BYTE
+        let valid_format =
+            (data.len() == LENGTH && data[0] == FORMAT_BYTE) ||
+            (data.len() == COMPRESSED_LENGTH &&
+             (data[0] == COMPRESSED_EVEN_BYTE || data[0] == COMPRESSED_ODD_BYTE));
+
+        valid_format && ecdsa::is_valid_public_key(data)
     }
 
-    /// Creates a PublicKey from a PrivateKey.
+    /// Creates an uncompressed PublicKey from a PrivateKey.
+    #[deprecated = "use PrivateKey::to_public_key_uncompressed() instead"]
     pub fn from_private_key(private_key: &PrivateKey) -> PublicKey {
-        PublicKey(ecdsa::derive_public_key(private_key.get_data()))
+        private_key.to_public_key_uncompressed()
+    }
+
+    /// Whether the public key is in compressed format.
+    pub fn is_compressed(&self) -> bool {
+        self.get_data().len() == COMPRESSED_LENGTH
     }
 
     /// Gets the raw public key as a slice of bytes.
@@ -73,6 +96,15 @@ mod tests {
         assert_eq!(public_key.unwrap().get_data(), data.as_slice());
     }
 
+    #[test]
+    fn test_new_compressed() {
+        let data = "03904B5CC692ECED64B2C04821F6A2D795BC3BC02F46165F95B817AF8A7810830D";
+        let data = data.from_hex().unwrap();
+        let public_key = PublicKey::new(data.as_slice());
+        assert!(public_key.is_some());
+        assert!(public_key.unwrap().is_compressed());
+    }
+
     #[test]
     fn test_new_invalid_length() {
         let data = "04904B5CC692ECED64B2C04821F6A2D795BC3BC02F46165F95B817AF8A78108301";
@@ -91,6 +123,24 @@ mod tests {
     }
 
     #[test]
+    fn test_new_all_zero() {
+        let mut data = Vec::from_elem(65, 0u8);
+        *data.index_mut(&0) = 0x04;
+        let public_key = PublicKey::new(data.as_slice());
+        assert!(public_key.is_none());
+    }
+
+    #[test]
+    fn test_new_not_on_curve() {
+        let data = "04904B5CC692ECED64B2C04821F6A2D795BC3BC02F46165F95B817AF8A7810830D\
+                      5BD4895315905B429EAEA4424908B3289668E46A2D1E451B2C9365120EB6D566";
+        let data = data.from_hex().unwrap();
+        let public_key = PublicKey::new(data.as_slice());
+        assert!(public_key.is_none());
+    }
+
+    #[test]
+    #[allow(deprecated)]
     fn test_from_private_key() {
         let data = "6B68589FA737367206B9E97DEE27828B9688FA3D034352DA0E79340B882582F9";
         let data = data.from_hex().unwrap();
@@ -0,0 +1,114 @@
+//! Experimental stealth-address-like payments, where a sender can derive a
+//! fresh one-time address for a receiver from the receiver's published scan
+//! and spend public keys, and the receiver can find it again using an
+//! ephemeral public key published by the sender.
+
+use util::ecdsa;
+use protocol::address::Address;
+use protocol::private_key::PrivateKey;
+use protocol::public_key::PublicKey;
+
+/// A receiver's pair of keys. The scan key is used to detect payments (and
+/// could be kept on a less secure machine), while the spend key is needed to
+/// actually spend them.
+pub struct StealthKeyPair {
+    scan_key: PrivateKey,
+    spend_key: PrivateKey
+}
+
+impl StealthKeyPair {
+    /// Creates a StealthKeyPair from existing scan and spend keys.
+    pub fn new(scan_key: PrivateKey, spend_key: PrivateKey) -> StealthKeyPair {
+        StealthKeyPair { scan_key: scan_key, spend_key: spend_key }
+    }
+
+    /// Generates a StealthKeyPair from two new random private keys.
+    pub fn generate() -> StealthKeyPair {
+        StealthKeyPair::new(PrivateKey::generate(), PrivateKey::generate())
+    }
+
+    /// Gets the public scan key, which senders need to make payments.
+    pub fn scan_public_key(&self) -> PublicKey {
+        self.scan_key.to_public_key()
+    }
+
+    /// Gets the public spend key, which senders need to make payments.
+    pub fn spend_public_key(&self) -> PublicKey {
+        self.spend_key.to_public_key()
+    }
+
+    /// Derives the one-time address that a sender paid to, given the sender's
+    /// ephemeral public key. Returns None if the ephemeral key isn't a valid
+    /// point on the curve.
+    pub fn derive_payment_address(&self, ephemeral: &PublicKey) -> Option<Address> {
+        // The receiver computes the shared secret from its scan key and the
+        // sender's ephemeral public key.
+        let shared_secret = ecdsa::ecdh(self.scan_key.get_data(), ephemeral.get_data());
+        let shared_secret = match shared_secret {
+            Some(secret) => secret,
+            None => return None
+        };
+
+        payment_address(&self.spend_public_key(), shared_secret.as_slice())
+    }
+}
+
+/// Derives the one-time address to pay to, given the receiver's public scan
+/// and spend keys and a fresh ephemeral private key. The public key of the
+/// ephemeral key must be published along with the payment, so the receiver
+/// can find it. Returns None if either public key isn't a valid point on the
+/// curve.
+pub fn derive_sender_address(scan_public_key: &PublicKey,
+                             spend_public_key: &PublicKey,
+                             ephemeral_key: &PrivateKey) -> Option<Address> {
+    // The sender computes the shared secret from the ephemeral key and the
+    // receiver's scan public key, which gives the same secret the receiver
+    // gets.
+    let shared_secret = ecdsa::ecdh(ephemeral_key.get_data(), scan_public_key.get_data());
+    let shared_secret = match shared_secret {
+        Some(secret) => secret,
+        None => return None
+    };
+
+    payment_address(spend_public_key, shared_secret.as_slice())
+}
+
+// Computes the payment address spend_public_key + c*G, where c is the shared
+// secret.
+fn payment_address(spend_public_key: &PublicKey, shared_secret: &[u8]) -> Option<Address> {
+    let payment_key = ecdsa::add_scalar_times_generator(spend_public_key.get_data(),
+                                                        shared_secret);
+
+    payment_key.and_then(|data| PublicKey::new(data.as_slice())).map(|key| key.to_address())
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol::private_key::PrivateKey;
+
+    use super::{StealthKeyPair, derive_sender_address};
+
+    #[test]
+    fn test_derive_payment_address() {
+        let receiver = StealthKeyPair::generate();
+        let ephemeral_key = PrivateKey::generate();
+
+        let sender_address = derive_sender_address(&receiver.scan_public_key(),
+                                                   &receiver.spend_public_key(),
+                                                   &ephemeral_key);
+        let receiver_address = receiver.derive_payment_address(&ephemeral_key.to_public_key());
+
+        assert!(sender_address.is_some());
+        assert_eq!(sender_address, receiver_address);
+    }
+
+    #[test]
+    fn test_derive_payment_address_differs_per_ephemeral_key() {
+        let receiver = StealthKeyPair::generate();
+
+        let first = receiver.derive_payment_address(&PrivateKey::generate().to_public_key());
+        let second = receiver.derive_payment_address(&PrivateKey::generate().to_public_key());
+
+        assert!(first != second);
+    }
+}
@@ -0,0 +1,89 @@
+//! Helpers for spending Taproot (BIP 341) outputs.
+
+/// The leaf version of tapscript (BIP 342) leaves.
+pub static TAPSCRIPT_LEAF_VERSION: u8 = 0xc0;
+
+// Length of an x-only public key, and of each hash in a merkle path.
+static KEY_LENGTH: uint = 32;
+
+/// The longest merkle path a control block can have, which is the deepest a
+/// Taproot script tree can be.
+pub static MAX_MERKLE_PATH_LENGTH: uint = 128;
+
+/// Assembles the control block that goes last in the witness of a script-path
+/// spend. It's laid out like this:
+///
+///     vkkkkkkkkkkkkkkkkkkkkkkkkkkkkkkkkpppp...
+///
+/// Where `v` is the leaf version combined with the parity of the output key's
+/// Y coordinate in the lowest bit, `k` is the 32-byte x-only internal key,
+/// and `p` is the merkle path from the leaf to the root, 32 bytes per level.
+/// A tree with a single leaf has an empty merkle path. Returns None if the
+/// leaf version is odd, the parity isn't 0 or 1, or the merkle path is longer
+/// than MAX_MERKLE_PATH_LENGTH.
+pub fn control_block(internal_key: &[u8, ..32], leaf_version: u8, merkle_path: &[[u8, ..32]],
+                     output_parity: u8) -> Option<Vec<u8>> {
+    if leaf_version & 0x01 != 0 || output_parity > 1 ||
+       merkle_path.len() > MAX_MERKLE_PATH_LENGTH {
+        return None;
+    }
+
+    let mut result = Vec::with_capacity(1 + KEY_LENGTH * (1 + merkle_path.len()));
+    result.push(leaf_version | output_parity);
+    result.push_all(internal_key.as_slice());
+    for hash in merkle_path.iter() {
+        result.push_all(hash.as_slice());
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{control_block, TAPSCRIPT_LEAF_VERSION, MAX_MERKLE_PATH_LENGTH};
+
+    #[test]
+    fn test_control_block_single_leaf() {
+        let internal_key = [0x11, ..32];
+        let block = control_block(&internal_key, TAPSCRIPT_LEAF_VERSION, &[], 1).unwrap();
+        assert_eq!(block.len(), 33);
+        assert_eq!(block[0], 0xc1);
+        assert_eq!(block.slice_from(1), internal_key.as_slice());
+
+        let block = control_block(&internal_key, TAPSCRIPT_LEAF_VERSION, &[], 0).unwrap();
+        assert_eq!(block[0], 0xc0);
+    }
+
+    #[test]
+    fn test_control_block_merkle_path() {
+        let internal_key = [0x11, ..32];
+        let path = [[0x22, ..32], [0x33, ..32]];
+        let block = control_block(&internal_key, TAPSCRIPT_LEAF_VERSION, &path, 0).unwrap();
+        assert_eq!(block.len(), 33 + 64);
+        assert_eq!(block.slice(1, 33), internal_key.as_slice());
+        assert_eq!(block.slice(33, 65), path[0].as_slice());
+        assert_eq!(block.slice(65, 97), path[1].as_slice());
+    }
+
+    #[test]
+    fn test_control_block_odd_leaf_version() {
+        let internal_key = [0x11, ..32];
+        assert_eq!(control_block(&internal_key, 0xc1, &[], 0), None);
+    }
+
+    #[test]
+    fn test_control_block_invalid_parity() {
+        let internal_key = [0x11, ..32];
+        assert_eq!(control_block(&internal_key, TAPSCRIPT_LEAF_VERSION, &[], 2), None);
+    }
+
+    #[test]
+    fn test_control_block_merkle_path_too_long() {
+        let internal_key = [0x11, ..32];
+        let path = Vec::from_elem(MAX_MERKLE_PATH_LENGTH + 1, [0x22, ..32]);
+        assert_eq!(control_block(&internal_key, TAPSCRIPT_LEAF_VERSION, path.as_slice(), 0), None);
+
+        let block = control_block(&internal_key, TAPSCRIPT_LEAF_VERSION,
+                                  path.slice_to(MAX_MERKLE_PATH_LENGTH), 0);
+        assert_eq!(block.map(|block| block.len()), Some(33 + 32 * MAX_MERKLE_PATH_LENGTH));
+    }
+}
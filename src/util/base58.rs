@@ -4,9 +4,25 @@ use num::Zero;
 use num::bigint::{BigUint,ToBigUint};
 use num::Integer;
 
+use util::wif;
+
 // Bitcoin's base-58 alphabet.
 static ALPHABET: &'static str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
 
+// The length of the checksum at the end of base-58 check encoded data.
+static CHECKSUM_LENGTH: uint = 4;
+
+/// The ways decoding a base-58 string with a checksum can fail.
+#[deriving(Clone, PartialEq, Show)]
+pub enum Error {
+    /// The string contains a non-base-58 character at the given position.
+    InvalidCharacter(uint),
+    /// The decoded data is too short to contain a checksum.
+    TooShort,
+    /// The checksum doesn't match the rest of the data.
+    InvalidChecksum
+}
+
 /// Encode a slice of bytes as base-58, preserving leading zero bytes.
 pub fn encode(data: &[u8]) -> String {
     // Count the leading zeroes.
@@ -69,6 +85,35 @@ pub fn decode(string: &str) -> Option<Vec<u8>> {
     Some(result)
 }
 
+/// Decode a base-58 string whose last four bytes are a checksum of the rest
+/// (as in Bitcoin addresses and WIF private keys), and return the data with the
+/// checksum stripped off.
+pub fn decode_check(string: &str) -> Result<Vec<u8>, Error> {
+    let data = match decode(string) {
+        Some(data) => data,
+        None => {
+            let position = string.chars().position(|ch| !ALPHABET.chars().any(|a| a == ch));
+            return Err(Error::InvalidCharacter(position.unwrap()));
+        }
+    };
+
+    if data.len() <= CHECKSUM_LENGTH {
+        return Err(Error::TooShort);
+    }
+
+    if !wif::check(data.as_slice()) {
+        return Err(Error::InvalidChecksum);
+    }
+
+    Ok(data.slice_to(data.len() - CHECKSUM_LENGTH).to_vec())
+}
+
+/// Decode a list of base-58 check encoded strings, returning a result for each
+/// one so that a bad entry doesn't stop the rest from being decoded.
+pub fn decode_check_batch(items: &[&str]) -> Vec<Result<Vec<u8>, Error>> {
+    items.iter().map(|item| decode_check(*item)).collect()
+}
+
 /// Encode a number as base-58.
 pub fn simple_encode(mut n: BigUint) -> String {
     let mut result = String::new();
@@ -101,7 +146,8 @@ pub fn simple_decode(string: &str) -> Option<BigUint> {
 
 #[cfg(test)]
 mod tests {
-    use super::{encode,decode};
+    use super::{encode,decode,decode_check,decode_check_batch};
+    use super::Error;
 
     #[test]
     fn test_encode_bitcoin_addresses() {
@@ -166,5 +212,27 @@ mod tests {
         assert!(decode("123OI321").is_none());
         assert!(decode("123 321").is_none());
     }
-}
 
+    #[test]
+    fn test_decode_check() {
+        let data: &[u8] = &[0x00,0x78,0x97,0x0e,0x37,0xa7,0xa4,0x71,0xdc,0x33,0xda,0xdb,
+                            0x51,0x42,0x06,0x84,0x31,0xb4,0x85,0x17,0xab];
+        assert_eq!(decode_check("1Bzd3YTSDwdFfAhMwYNV6A3K5hwYHbaUeG").unwrap().as_slice(), data);
+    }
+
+    #[test]
+    fn test_decode_check_batch() {
+        let items = ["1Bzd3YTSDwdFfAhMwYNV6A3K5hwYHbaUeG",
+                     "1Bzd3YTSDwdFfAhMwYNV6A3K5hwYHbaUeH",
+                     "1Bzd3YTSDwdFfAhMwYNV0A3K5hwYHbaUeG",
+                     "1111",
+                     "112gHKoeKQ3PEXEdAZeC5tBoonPR2UCQot"];
+        let results = decode_check_batch(&items);
+        assert_eq!(results.len(), 5);
+        assert!(results[0].is_ok());
+        assert_eq!(results[1], Err(Error::InvalidChecksum));
+        assert_eq!(results[2], Err(Error::InvalidCharacter(20)));
+        assert_eq!(results[3], Err(Error::TooShort));
+        assert!(results[4].is_ok());
+    }
+}
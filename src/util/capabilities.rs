@@ -0,0 +1,123 @@
+//! Detection of which algorithms the linked OpenSSL library actually
+//! provides. Some hardened builds of OpenSSL disable algorithms like
+//! RIPEMD-160, and hashing or encrypting with a disabled algorithm silently
+//! produces garbage instead of failing. So each algorithm is checked against a
+//! known answer before we rely on it.
+
+use openssl;
+use openssl::crypto::hash::HashType::RIPEMD160;
+use openssl::crypto::symm::Type::AES_256_CBC;
+use serialize::hex::FromHex;
+
+use std::sync::{Once, ONCE_INIT};
+
+/// The available implementations of RIPEMD-160.
+#[deriving(Clone, Copy, PartialEq, Show)]
+pub enum Ripemd160Impl {
+    /// OpenSSL's implementation, used whenever it's available.
+    OpenSsl,
+    /// The pure Rust implementation in `util::ripemd160`.
+    BuiltIn
+}
+
+// Guards the one-time detection of the RIPEMD-160 implementation.
+static DETECT_RIPEMD160: Once = ONCE_INIT;
+
+// The detected RIPEMD-160 implementation. Only written once, inside
+// DETECT_RIPEMD160.
+static mut RIPEMD160_IMPL: Ripemd160Impl = Ripemd160Impl::BuiltIn;
+
+/// Returns the RIPEMD-160 implementation to use: OpenSSL's if it works,
+/// otherwise the built-in one. OpenSSL is only checked the first time this is
+/// called, since the linked library can't change while we're running.
+pub fn ripemd160_impl() -> Ripemd160Impl {
+    unsafe {
+        DETECT_RIPEMD160.call_once(|| {
+            RIPEMD160_IMPL = if openssl_has_ripemd160() {
+                Ripemd160Impl::OpenSsl
+            } else {
+                Ripemd160Impl::BuiltIn
+            };
+        });
+        RIPEMD160_IMPL
+    }
+}
+
+// Guards the one-time check of the algorithms used for wallet encryption.
+static DETECT_ENCRYPTION: Once = ONCE_INIT;
+
+// The name of the first wallet encryption algorithm found missing, if any.
+// Only written once, inside DETECT_ENCRYPTION.
+static mut MISSING_ENCRYPTION_ALGORITHM: Option<&'static str> = None;
+
+/// Returns the name of an algorithm needed for wallet encryption that OpenSSL
+/// lacks, or None if AES-256-CBC and PBKDF2-HMAC-SHA1 both work. Like
+/// ripemd160_impl(), OpenSSL is only checked the first time this is called.
+pub fn missing_encryption_algorithm() -> Option<&'static str> {
+    unsafe {
+        DETECT_ENCRYPTION.call_once(|| {
+            MISSING_ENCRYPTION_ALGORITHM = if !openssl_has_aes_256_cbc() {
+                Some("AES-256-CBC")
+            } else if !openssl_has_pbkdf2_hmac_sha1() {
+                Some("PBKDF2-HMAC-SHA1")
+            } else {
+                None
+            };
+        });
+        MISSING_ENCRYPTION_ALGORITHM
+    }
+}
+
+/// Checks whether OpenSSL's RIPEMD-160 gives the right hash of "abc".
+pub fn openssl_has_ripemd160() -> bool {
+    let expected = "8eb208f7e05d987a9b044a8e98c6b087f15a0bfc".from_hex().unwrap();
+    openssl::crypto::hash::hash(RIPEMD160, b"abc".as_slice()) == expected
+}
+
+/// Checks whether OpenSSL's AES-256-CBC gives the right ciphertext for a block
+/// of zeroes encrypted with an all-zero key and iv.
+pub fn openssl_has_aes_256_cbc() -> bool {
+    let key = Vec::from_elem(32, 0u8);
+    let iv = Vec::from_elem(16, 0u8);
+    let plaintext = Vec::from_elem(16, 0u8);
+    let expected = "dc95c078a2408989ad48a21492842087\
+                    f3c003ddc4a7b8a94baedffc3d214c38".from_hex().unwrap();
+    let ciphertext = openssl::crypto::symm::encrypt(AES_256_CBC, key.as_slice(), iv,
+                                                    plaintext.as_slice());
+    ciphertext == expected
+}
+
+/// Checks whether OpenSSL's PBKDF2-HMAC-SHA1 gives the right key for the first
+/// test vector in RFC 6070.
+pub fn openssl_has_pbkdf2_hmac_sha1() -> bool {
+    let expected = "0c60c80f961f0e71f3a9b524af6012062fe037a6".from_hex().unwrap();
+    openssl::crypto::pkcs5::pbkdf2_hmac_sha1("password", b"salt".as_slice(), 1, 20) == expected
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{openssl_has_ripemd160, openssl_has_aes_256_cbc, openssl_has_pbkdf2_hmac_sha1};
+    use super::{ripemd160_impl, Ripemd160Impl, missing_encryption_algorithm};
+
+    #[test]
+    fn test_openssl_algorithms() {
+        // These are all available in any normal build of OpenSSL.
+        assert!(openssl_has_ripemd160());
+        assert!(openssl_has_aes_256_cbc());
+        assert!(openssl_has_pbkdf2_hmac_sha1());
+    }
+
+    #[test]
+    fn test_ripemd160_impl() {
+        assert_eq!(ripemd160_impl(), Ripemd160Impl::OpenSsl);
+        // Later calls reuse the first result.
+        assert_eq!(ripemd160_impl(), Ripemd160Impl::OpenSsl);
+    }
+
+    #[test]
+    fn test_missing_encryption_algorithm() {
+        assert_eq!(missing_encryption_algorithm(), None);
+        // Later calls reuse the first result.
+        assert_eq!(missing_encryption_algorithm(), None);
+    }
+}
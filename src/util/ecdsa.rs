@@ -1,12 +1,23 @@
 //! Functions that work with elliptic curve keys and signatures.
 
-use libc::{c_int, c_uchar, size_t};
+use libc::{c_int, c_uchar, c_ulong, size_t};
+use openssl;
+use openssl::crypto::hash::HashType::SHA256;
 use std::ptr;
 
 // OpenSSL's numeric code for the particular elliptic curve that Bitcoin uses.
 #[allow(non_upper_case_globals)]
 static NID_secp256k1: int = 714;
 
+// The order of the secp256k1 curve, i.e. the number of points on it. Valid
+// scalars (private keys) are in the range [1, CURVE_ORDER - 1].
+static CURVE_ORDER: &'static [u8] = &[
+    0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,
+    0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xfe,
+    0xba,0xae,0xdc,0xe6,0xaf,0x48,0xa0,0x3b,
+    0xbf,0xd2,0x5e,0x8c,0xd0,0x36,0x41,0x41
+];
+
 #[allow(non_camel_case_types)]
 #[repr(C)]
 struct EC_GROUP;
@@ -48,12 +59,41 @@ extern {
                           buf: *mut c_uchar,
                           len: size_t,
                           ctx: *mut BN_CTX) -> size_t;
+    fn EC_POINT_oct2point(group: *const EC_GROUP,
+                          p: *mut EC_POINT,
+                          buf: *const c_uchar,
+                          len: size_t,
+                          ctx: *mut BN_CTX) -> c_int;
+    fn EC_POINT_is_at_infinity(group: *const EC_GROUP, p: *const EC_POINT) -> c_int;
 
     fn EC_GROUP_new_by_curve_name(nid: c_int) -> *mut EC_GROUP;
+    fn EC_GROUP_free(group: *mut EC_GROUP);
 
     fn BN_new() -> *mut BIGNUM;
     fn BN_free(a: *mut BIGNUM);
     fn BN_bin2bn(s: *const c_uchar, len: c_int, ret: *mut BIGNUM) -> *mut BIGNUM;
+    fn BN_set_word(a: *mut BIGNUM, w: c_ulong) -> c_int;
+    fn BN_bn2bin(a: *const BIGNUM, to: *mut c_uchar) -> c_int;
+    fn BN_num_bits(a: *const BIGNUM) -> c_int;
+    fn BN_mod_add(r: *mut BIGNUM,
+                  a: *const BIGNUM,
+                  b: *const BIGNUM,
+                  m: *const BIGNUM,
+                  ctx: *mut BN_CTX) -> c_int;
+    fn BN_mod_sub(r: *mut BIGNUM,
+                  a: *const BIGNUM,
+                  b: *const BIGNUM,
+                  m: *const BIGNUM,
+                  ctx: *mut BN_CTX) -> c_int;
+    fn BN_mod_mul(r: *mut BIGNUM,
+                  a: *const BIGNUM,
+                  b: *const BIGNUM,
+                  m: *const BIGNUM,
+                  ctx: *mut BN_CTX) -> c_int;
+    fn BN_mod_inverse(r: *mut BIGNUM,
+                      a: *const BIGNUM,
+                      n: *const BIGNUM,
+                      ctx: *mut BN_CTX) -> *mut BIGNUM;
 
     fn BN_CTX_new() -> *mut BN_CTX;
     fn BN_CTX_free(c: *mut BN_CTX);
@@ -61,7 +101,7 @@ extern {
 
 /// Takes a 32-byte Bitcoin private key, and derives the 65-byte uncompressed
 /// public key from it. Assumes the private key is valid, i.e. is 32 bytes long
-/// and falls within the range defined in `src/protocol/private_key.rs`.
+/// and falls within the range checked by is_valid_scalar().
 pub fn derive_public_key(private_key: &[u8]) -> Vec<u8> {
     assert!(private_key.len() == 32u);
     unsafe {
@@ -92,9 +132,274 @@ pub fn derive_public_key(private_key: &[u8]) -> Vec<u8> {
     }
 }
 
+/// Generates a random 32-byte scalar in the range [1, n - 1], where n is the
+/// order of the curve, using OpenSSL's secure random bytes generator. Random
+/// bytes that fall outside that range are thrown away and regenerated, which
+/// almost never happens for secp256k1 since n is so close to 2^256.
+pub fn random_scalar() -> Vec<u8> {
+    sample_scalar(|| openssl::crypto::rand::rand_bytes(32))
+}
+
+// Draws 32-byte candidates from the given source until one is a valid scalar,
+// and returns it.
+fn sample_scalar(next_candidate: || -> Vec<u8>) -> Vec<u8> {
+    loop {
+        let scalar = next_candidate();
+        if is_valid_scalar(scalar.as_slice()) {
+            return scalar;
+        }
+    }
+}
+
+/// Checks whether the given data is a valid 32-byte scalar, i.e. is in the
+/// range [1, n - 1] where n is the order of the curve.
+pub fn is_valid_scalar(data: &[u8]) -> bool {
+    data.len() == 32u &&
+    data.iter().any(|&byte| byte != 0) &&
+    data < CURVE_ORDER
+}
+
+/// Multiplies the point represented by the given public key (in any format) by
+/// a 32-byte scalar, and returns the resulting point as a 65-byte uncompressed
+/// public key. Returns None if the public key isn't a valid point on the
+/// curve, or if the result is the point at infinity.
+pub fn multiply_point(public_key: &[u8], scalar: &[u8]) -> Option<Vec<u8>> {
+    assert!(scalar.len() == 32u);
+    unsafe {
+        let curve = EC_GROUP_new_by_curve_name(NID_secp256k1 as c_int) as *const EC_GROUP;
+        let ctx = BN_CTX_new();
+
+        let point = decode_point(curve, public_key, ctx);
+        if point.is_null() {
+            BN_CTX_free(ctx);
+            EC_GROUP_free(curve as *mut EC_GROUP);
+            return None;
+        }
+
+        // EC_POINT_mul() computes n*G + m*q, so leave n out to get m*q.
+        let m = BN_bin2bn(scalar.as_ptr(), scalar.len() as c_int, BN_new());
+        let product = EC_POINT_new(curve);
+        EC_POINT_mul(curve, product, ptr::null(), point as *const EC_POINT, m as *const BIGNUM, ctx);
+
+        let result = encode_point(curve, product as *const EC_POINT, ctx);
+
+        BN_free(m);
+        EC_POINT_free(product);
+        EC_POINT_free(point);
+        BN_CTX_free(ctx);
+        EC_GROUP_free(curve as *mut EC_GROUP);
+
+        result
+    }
+}
+
+/// Adds `scalar * G` to the point represented by the given public key (in any
+/// format), where `G` is the generator of the curve, and returns the resulting
+/// point as a 65-byte uncompressed public key. Returns None if the public key
+/// isn't a valid point on the curve, or if the result is the point at
+/// infinity.
+pub fn add_scalar_times_generator(public_key: &[u8], scalar: &[u8]) -> Option<Vec<u8>> {
+    assert!(scalar.len() == 32u);
+    unsafe {
+        let curve = EC_GROUP_new_by_curve_name(NID_secp256k1 as c_int) as *const EC_GROUP;
+        let ctx = BN_CTX_new();
+
+        let point = decode_point(curve, public_key, ctx);
+        if point.is_null() {
+            BN_CTX_free(ctx);
+            EC_GROUP_free(curve as *mut EC_GROUP);
+            return None;
+        }
+
+        // EC_POINT_mul() computes n*G + m*q, so use m = 1 to get n*G + q.
+        let n = BN_bin2bn(scalar.as_ptr(), scalar.len() as c_int, BN_new());
+        let one = BN_new();
+        BN_set_word(one, 1);
+        let sum = EC_POINT_new(curve);
+        EC_POINT_mul(curve, sum, n as *const BIGNUM, point as *const EC_POINT, one as *const BIGNUM, ctx);
+
+        let result = encode_point(curve, sum as *const EC_POINT, ctx);
+
+        BN_free(one);
+        BN_free(n);
+        EC_POINT_free(sum);
+        EC_POINT_free(point);
+        BN_CTX_free(ctx);
+        EC_GROUP_free(curve as *mut EC_GROUP);
+
+        result
+    }
+}
+
+/// Computes an ECDH shared secret from one party's 32-byte private key and the
+/// other party's public key (in any format). The secret is the SHA-256 hash of
+/// the compressed form of `private_key * public_key`, so both parties get the
+/// same 32 bytes. Returns None if either key is invalid.
+pub fn ecdh(private_key: &[u8], public_key: &[u8]) -> Option<Vec<u8>> {
+    if !is_valid_scalar(private_key) {
+        return None;
+    }
+
+    multiply_point(public_key, private_key).map(|point| {
+        let compressed = compress_public_key(point.as_slice());
+        openssl::crypto::hash::hash(SHA256, compressed.as_slice())
+    })
+}
+
+/// Checks whether the given public key (in any format) is the encoding of a
+/// point on the curve other than the point at infinity. This rejects keys with
+/// the right format byte and length but garbage coordinates, such as 0x04
+/// followed by all zeroes.
+pub fn is_valid_public_key(public_key: &[u8]) -> bool {
+    unsafe {
+        let curve = EC_GROUP_new_by_curve_name(NID_secp256k1 as c_int) as *const EC_GROUP;
+        let ctx = BN_CTX_new();
+
+        let point = decode_point(curve, public_key, ctx);
+        let valid = !point.is_null() && EC_POINT_is_at_infinity(curve, point as *const EC_POINT) != 1;
+
+        if !point.is_null() {
+            EC_POINT_free(point);
+        }
+        BN_CTX_free(ctx);
+        EC_GROUP_free(curve as *mut EC_GROUP);
+
+        valid
+    }
+}
+
+/// Converts a 65-byte uncompressed public key into the 33-byte compressed
+/// format, which is just the X coordinate prefixed by 0x02 or 0x03 depending
+/// on whether the Y coordinate is even or odd.
+pub fn compress_public_key(public_key: &[u8]) -> Vec<u8> {
+    assert!(public_key.len() == 65u && public_key[0] == 0x04);
+    let mut result = Vec::with_capacity(33);
+    result.push(0x02 | (public_key[64] & 0x01));
+    result.push_all(public_key.slice(1, 33));
+    result
+}
+
+/// Negates a scalar modulo the order of the curve, i.e. computes
+/// `(n - scalar) mod n`. Negating zero gives zero.
+pub fn scalar_negate(scalar: &[u8, ..32]) -> [u8, ..32] {
+    unsafe { scalar_op(CURVE_ORDER, scalar.as_slice(), BN_mod_sub) }
+}
+
+/// Computes `(a + b) mod n`, where n is the order of the curve.
+pub fn scalar_add(a: &[u8, ..32], b: &[u8, ..32]) -> [u8, ..32] {
+    unsafe { scalar_op(a.as_slice(), b.as_slice(), BN_mod_add) }
+}
+
+/// Computes `(a * b) mod n`, where n is the order of the curve.
+pub fn scalar_mul(a: &[u8, ..32], b: &[u8, ..32]) -> [u8, ..32] {
+    unsafe { scalar_op(a.as_slice(), b.as_slice(), BN_mod_mul) }
+}
+
+/// Computes the multiplicative inverse of a scalar modulo the order of the
+/// curve, i.e. the scalar `x` such that `(scalar * x) mod n == 1`. Returns
+/// None if there is no inverse, which only happens when the scalar is a
+/// multiple of n (e.g. zero).
+pub fn scalar_inverse(scalar: &[u8, ..32]) -> Option<[u8, ..32]> {
+    unsafe {
+        let ctx = BN_CTX_new();
+        let order = BN_bin2bn(CURVE_ORDER.as_ptr(), CURVE_ORDER.len() as c_int, BN_new());
+        let a = BN_bin2bn(scalar.as_ptr(), scalar.len() as c_int, BN_new());
+
+        let inverse = BN_new();
+        let result = if BN_mod_inverse(inverse, a as *const BIGNUM, order as *const BIGNUM, ctx).is_null() {
+            None
+        } else {
+            Some(encode_scalar(inverse as *const BIGNUM))
+        };
+
+        BN_free(inverse);
+        BN_free(a);
+        BN_free(order);
+        BN_CTX_free(ctx);
+
+        result
+    }
+}
+
+/// Checks that a DER-encoded signature's outer SEQUENCE header is consistent
+/// with its actual length, i.e. that it starts with 0x30 followed by a length
+/// byte equal to the number of bytes after it. This is a cheap way to catch
+/// truncated or padded signatures before doing any real work with them. It
+/// doesn't check the integers inside the sequence.
+pub fn der_length_valid(signature: &[u8]) -> bool {
+    signature.len() >= 2 &&
+    signature[0] == 0x30 &&
+    signature[1] as uint == signature.len() - 2
+}
+
+// Decodes a public key into a newly allocated EC_POINT, which the caller must
+// free. Returns a null pointer if the data isn't a valid point on the curve.
+unsafe fn decode_point(curve: *const EC_GROUP, data: &[u8], ctx: *mut BN_CTX) -> *mut EC_POINT {
+    let point = EC_POINT_new(curve);
+    if EC_POINT_oct2point(curve, point, data.as_ptr(), data.len() as size_t, ctx) != 1 {
+        EC_POINT_free(point);
+        return ptr::null_mut();
+    }
+    point
+}
+
+// Encodes an EC_POINT as a 65-byte uncompressed public key. Returns None for
+// the point at infinity, which has no such encoding.
+unsafe fn encode_point(curve: *const EC_GROUP, point: *const EC_POINT, ctx: *mut BN_CTX) -> Option<Vec<u8>> {
+    if EC_POINT_is_at_infinity(curve, point) == 1 {
+        return None;
+    }
+
+    let mut result = Vec::from_elem(65, 0u8);
+    EC_POINT_point2oct(curve, point, point_conversion_form_t::POINT_CONVERSION_UNCOMPRESSED, result.as_mut_ptr(), 65, ctx);
+    Some(result)
+}
+
+// The signature shared by OpenSSL's modular arithmetic functions, which compute
+// `r = (a op b) mod m`.
+type ModularOp = unsafe extern "C" fn(r: *mut BIGNUM,
+                                      a: *const BIGNUM,
+                                      b: *const BIGNUM,
+                                      m: *const BIGNUM,
+                                      ctx: *mut BN_CTX) -> c_int;
+
+// Applies one of OpenSSL's modular arithmetic functions to two big-endian
+// numbers, modulo the order of the curve, and returns the result as a 32-byte
+// scalar.
+unsafe fn scalar_op(a: &[u8], b: &[u8], op: ModularOp) -> [u8, ..32] {
+    let ctx = BN_CTX_new();
+    let order = BN_bin2bn(CURVE_ORDER.as_ptr(), CURVE_ORDER.len() as c_int, BN_new());
+    let a = BN_bin2bn(a.as_ptr(), a.len() as c_int, BN_new());
+    let b = BN_bin2bn(b.as_ptr(), b.len() as c_int, BN_new());
+
+    let r = BN_new();
+    op(r, a as *const BIGNUM, b as *const BIGNUM, order as *const BIGNUM, ctx);
+    let result = encode_scalar(r as *const BIGNUM);
+
+    BN_free(r);
+    BN_free(b);
+    BN_free(a);
+    BN_free(order);
+    BN_CTX_free(ctx);
+
+    result
+}
+
+// Encodes a bignum in the range [0, 2^256) as a 32-byte big-endian scalar,
+// padding it on the left with zero bytes.
+unsafe fn encode_scalar(n: *const BIGNUM) -> [u8, ..32] {
+    let mut result = [0u8, ..32];
+    let length = ((BN_num_bits(n) + 7) / 8) as uint;
+    assert!(length <= 32);
+    BN_bn2bin(n, result.as_mut_ptr().offset((32 - length) as int));
+    result
+}
+
 #[cfg(test)]
 mod tests {
-    use super::derive_public_key;
+    use super::{derive_public_key, multiply_point, add_scalar_times_generator, compress_public_key};
+    use super::{ecdh, random_scalar, sample_scalar, is_valid_scalar, is_valid_public_key, CURVE_ORDER};
+    use super::{der_length_valid, scalar_negate, scalar_add, scalar_mul, scalar_inverse};
 
     #[test]
     fn test_derive_public_key() {
@@ -110,5 +415,255 @@ mod tests {
 
         assert_eq!(derived_public_key.as_slice(), actual_public_key);
     }
-}
 
+    #[test]
+    fn test_random_scalar() {
+        let scalar = random_scalar();
+        assert!(is_valid_scalar(scalar.as_slice()));
+        assert!(random_scalar() != scalar);
+    }
+
+    #[test]
+    fn test_sample_scalar() {
+        // Zero, n, and n + 1 are all out of range, and should be skipped.
+        let zero = Vec::from_elem(32, 0u8);
+        let order = CURVE_ORDER.to_vec();
+        let mut order_plus_one = CURVE_ORDER.to_vec();
+        *order_plus_one.index_mut(&31) = 0x42;
+        let mut valid = CURVE_ORDER.to_vec();
+        *valid.index_mut(&31) = 0x40;
+
+        let mut candidates = vec![zero, order, order_plus_one, valid.clone()].into_iter();
+        let scalar = sample_scalar(|| candidates.next().unwrap());
+        assert_eq!(scalar, valid);
+        assert!(candidates.next().is_none());
+    }
+
+    #[test]
+    fn test_is_valid_scalar() {
+        let mut scalar = CURVE_ORDER.to_vec();
+        assert!(!is_valid_scalar(scalar.as_slice()));
+
+        *scalar.index_mut(&31) = 0x40;
+        assert!(is_valid_scalar(scalar.as_slice()));
+
+        assert!(!is_valid_scalar(Vec::from_elem(32, 0u8).as_slice()));
+        assert!(!is_valid_scalar(scalar.slice_to(31)));
+    }
+
+    #[test]
+    fn test_multiply_point() {
+        // Multiplying the generator point by a private key is the same as
+        // deriving its public key.
+        let generator = derive_public_key(&[0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,
+                                            0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,
+                                            0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,
+                                            0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x01]);
+        let private_key: &[u8] =
+            &[0xf7,0x47,0x65,0x32,0xfe,0x57,0x53,0xeb,0xcb,0xea,0x26,0xfe,0x02,0xff,0xf1,0x8b,
+              0xf0,0x15,0x54,0x6f,0x85,0xca,0xf7,0x8a,0xc8,0xd5,0x99,0x54,0x7f,0x7d,0x3a,0xac];
+        let product = multiply_point(generator.as_slice(), private_key);
+        assert_eq!(product, Some(derive_public_key(private_key)));
+    }
+
+    #[test]
+    fn test_multiply_point_invalid() {
+        let mut public_key = Vec::from_elem(65, 0u8);
+        *public_key.index_mut(&0) = 0x04;
+        let scalar = Vec::from_elem(32, 1u8);
+        assert!(multiply_point(public_key.as_slice(), scalar.as_slice()).is_none());
+    }
+
+    #[test]
+    fn test_add_scalar_times_generator() {
+        // 1*G + 1*G should be the same as 2*G.
+        let mut one = Vec::from_elem(32, 0u8);
+        *one.index_mut(&31) = 1;
+        let mut two = Vec::from_elem(32, 0u8);
+        *two.index_mut(&31) = 2;
+        let generator = derive_public_key(one.as_slice());
+        let sum = add_scalar_times_generator(generator.as_slice(), one.as_slice());
+        assert_eq!(sum, Some(derive_public_key(two.as_slice())));
+    }
+
+    #[test]
+    fn test_ecdh() {
+        let alice_private: &[u8] =
+            &[0xf7,0x47,0x65,0x32,0xfe,0x57,0x53,0xeb,0xcb,0xea,0x26,0xfe,0x02,0xff,0xf1,0x8b,
+              0xf0,0x15,0x54,0x6f,0x85,0xca,0xf7,0x8a,0xc8,0xd5,0x99,0x54,0x7f,0x7d,0x3a,0xac];
+        let bob_private: &[u8] =
+            &[0x6b,0x68,0x58,0x9f,0xa7,0x37,0x36,0x72,0x06,0xb9,0xe9,0x7d,0xee,0x27,0x82,0x8b,
+              0x96,0x88,0xfa,0x3d,0x03,0x43,0x52,0xda,0x0e,0x79,0x34,0x0b,0x88,0x25,0x82,0xf9];
+        let alice_public = derive_public_key(alice_private);
+        let bob_public = derive_public_key(bob_private);
+
+        let alice_secret = ecdh(alice_private, bob_public.as_slice());
+        let bob_secret = ecdh(bob_private, alice_public.as_slice());
+
+        assert!(alice_secret.is_some());
+        assert_eq!(alice_secret.as_ref().unwrap().len(), 32);
+        assert_eq!(alice_secret, bob_secret);
+    }
+
+    #[test]
+    fn test_ecdh_invalid_keys() {
+        let private_key: &[u8] =
+            &[0xf7,0x47,0x65,0x32,0xfe,0x57,0x53,0xeb,0xcb,0xea,0x26,0xfe,0x02,0xff,0xf1,0x8b,
+              0xf0,0x15,0x54,0x6f,0x85,0xca,0xf7,0x8a,0xc8,0xd5,0x99,0x54,0x7f,0x7d,0x3a,0xac];
+        let public_key = derive_public_key(private_key);
+
+        let zero = Vec::from_elem(32, 0u8);
+        assert!(ecdh(zero.as_slice(), public_key.as_slice()).is_none());
+        assert!(ecdh(CURVE_ORDER, public_key.as_slice()).is_none());
+        assert!(ecdh(private_key.slice_to(31), public_key.as_slice()).is_none());
+
+        let mut invalid_public_key = Vec::from_elem(65, 0u8);
+        *invalid_public_key.index_mut(&0) = 0x04;
+        assert!(ecdh(private_key, invalid_public_key.as_slice()).is_none());
+    }
+
+    #[test]
+    fn test_is_valid_public_key() {
+        let private_key: &[u8] =
+            &[0xf7,0x47,0x65,0x32,0xfe,0x57,0x53,0xeb,0xcb,0xea,0x26,0xfe,0x02,0xff,0xf1,0x8b,
+              0xf0,0x15,0x54,0x6f,0x85,0xca,0xf7,0x8a,0xc8,0xd5,0x99,0x54,0x7f,0x7d,0x3a,0xac];
+        let public_key = derive_public_key(private_key);
+        assert!(is_valid_public_key(public_key.as_slice()));
+        assert!(is_valid_public_key(compress_public_key(public_key.as_slice()).as_slice()));
+
+        let mut not_on_curve = public_key.clone();
+        *not_on_curve.index_mut(&64) ^= 0x01;
+        assert!(!is_valid_public_key(not_on_curve.as_slice()));
+
+        let mut all_zero = Vec::from_elem(65, 0u8);
+        *all_zero.index_mut(&0) = 0x04;
+        assert!(!is_valid_public_key(all_zero.as_slice()));
+    }
+
+    #[test]
+    fn test_scalar_negate() {
+        let scalar: [u8, ..32] =
+            [0xf7,0x47,0x65,0x32,0xfe,0x57,0x53,0xeb,0xcb,0xea,0x26,0xfe,0x02,0xff,0xf1,0x8b,
+             0xf0,0x15,0x54,0x6f,0x85,0xca,0xf7,0x8a,0xc8,0xd5,0x99,0x54,0x7f,0x7d,0x3a,0xac];
+        let negated = scalar_negate(&scalar);
+        assert!(negated != scalar);
+        assert_eq!(scalar_negate(&negated), scalar);
+
+        // scalar + -scalar should be exactly n, which is 0 mod n.
+        let mut sum = [0u8, ..32];
+        let mut carry = 0u;
+        for i in range(0u, 32).rev() {
+            let digit = scalar[i] as uint + negated[i] as uint + carry;
+            sum[i] = digit as u8;
+            carry = digit >> 8;
+        }
+        assert_eq!(carry, 0);
+        assert_eq!(sum.as_slice(), CURVE_ORDER);
+    }
+
+    #[test]
+    fn test_scalar_negate_small() {
+        let mut one = [0u8, ..32];
+        one[31] = 1;
+        let mut minus_one = [0u8, ..32];
+        for (dest, src) in minus_one.iter_mut().zip(CURVE_ORDER.iter()) {
+            *dest = *src;
+        }
+        minus_one[31] = 0x40;
+        assert_eq!(scalar_negate(&one), minus_one);
+
+        assert_eq!(scalar_negate(&[0u8, ..32]), [0u8, ..32]);
+    }
+
+    #[test]
+    fn test_scalar_add() {
+        let a: [u8, ..32] =
+            [0xf7,0x47,0x65,0x32,0xfe,0x57,0x53,0xeb,0xcb,0xea,0x26,0xfe,0x02,0xff,0xf1,0x8b,
+             0xf0,0x15,0x54,0x6f,0x85,0xca,0xf7,0x8a,0xc8,0xd5,0x99,0x54,0x7f,0x7d,0x3a,0xac];
+        let b: [u8, ..32] =
+            [0x6b,0x68,0x58,0x9f,0xa7,0x37,0x36,0x72,0x06,0xb9,0xe9,0x7d,0xee,0x27,0x82,0x8b,
+             0x96,0x88,0xfa,0x3d,0x03,0x43,0x52,0xda,0x0e,0x79,0x34,0x0b,0x88,0x25,0x82,0xf9];
+        let c = [0xab, ..32];
+
+        assert_eq!(scalar_add(&scalar_add(&a, &b), &c), scalar_add(&a, &scalar_add(&b, &c)));
+        assert_eq!(scalar_add(&a, &b), scalar_add(&b, &a));
+        assert_eq!(scalar_add(&a, &scalar_negate(&a)), [0u8, ..32]);
+    }
+
+    #[test]
+    fn test_scalar_mul() {
+        let a: [u8, ..32] =
+            [0xf7,0x47,0x65,0x32,0xfe,0x57,0x53,0xeb,0xcb,0xea,0x26,0xfe,0x02,0xff,0xf1,0x8b,
+             0xf0,0x15,0x54,0x6f,0x85,0xca,0xf7,0x8a,0xc8,0xd5,0x99,0x54,0x7f,0x7d,0x3a,0xac];
+        let mut one = [0u8, ..32];
+        one[31] = 1;
+        let mut two = [0u8, ..32];
+        two[31] = 2;
+
+        assert_eq!(scalar_mul(&a, &one), a);
+        assert_eq!(scalar_mul(&a, &two), scalar_add(&a, &a));
+        assert_eq!(scalar_mul(&a, &[0u8, ..32]), [0u8, ..32]);
+    }
+
+    #[test]
+    fn test_scalar_inverse() {
+        let a: [u8, ..32] =
+            [0xf7,0x47,0x65,0x32,0xfe,0x57,0x53,0xeb,0xcb,0xea,0x26,0xfe,0x02,0xff,0xf1,0x8b,
+             0xf0,0x15,0x54,0x6f,0x85,0xca,0xf7,0x8a,0xc8,0xd5,0x99,0x54,0x7f,0x7d,0x3a,0xac];
+        let mut one = [0u8, ..32];
+        one[31] = 1;
+        let inverse = scalar_inverse(&a).unwrap();
+        assert_eq!(scalar_mul(&a, &inverse), one);
+
+        // The inverse of 2 is (n + 1) / 2.
+        let mut two = [0u8, ..32];
+        two[31] = 2;
+        let expected: [u8, ..32] =
+            [0x7f,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,0xff,
+             0x5d,0x57,0x6e,0x73,0x57,0xa4,0x50,0x1d,0xdf,0xe9,0x2f,0x46,0x68,0x1b,0x20,0xa1];
+        assert_eq!(scalar_inverse(&two), Some(expected));
+
+        assert_eq!(scalar_inverse(&[0u8, ..32]), None);
+    }
+
+    #[test]
+    fn test_der_length_valid() {
+        // SEQUENCE { INTEGER 0x3456..., INTEGER 0x1234... }
+        let signature: &[u8] =
+            &[0x30,0x0c,0x02,0x04,0x34,0x56,0x78,0x9a,0x02,0x04,0x12,0x34,0x56,0x78];
+        assert!(der_length_valid(signature));
+
+        // Truncated.
+        assert!(!der_length_valid(signature.slice_to(13)));
+
+        // Padded with an extra byte.
+        let mut padded = signature.to_vec();
+        padded.push(0x00);
+        assert!(!der_length_valid(padded.as_slice()));
+
+        // Declared length is wrong.
+        let mut wrong_length = signature.to_vec();
+        *wrong_length.index_mut(&1) = 0x0d;
+        assert!(!der_length_valid(wrong_length.as_slice()));
+
+        // Not a SEQUENCE.
+        let mut not_sequence = signature.to_vec();
+        *not_sequence.index_mut(&0) = 0x31;
+        assert!(!der_length_valid(not_sequence.as_slice()));
+
+        assert!(!der_length_valid(&[0x30]));
+        assert!(!der_length_valid(&[]));
+    }
+
+    #[test]
+    fn test_compress_public_key() {
+        let private_key: &[u8] =
+            &[0xf7,0x47,0x65,0x32,0xfe,0x57,0x53,0xeb,0xcb,0xea,0x26,0xfe,0x02,0xff,0xf1,0x8b,
+              0xf0,0x15,0x54,0x6f,0x85,0xca,0xf7,0x8a,0xc8,0xd5,0x99,0x54,0x7f,0x7d,0x3a,0xac];
+        let public_key = derive_public_key(private_key);
+        let compressed = compress_public_key(public_key.as_slice());
+        assert_eq!(compressed.len(), 33);
+        assert_eq!(compressed[0], 0x03);
+        assert_eq!(compressed.slice_from(1), public_key.slice(1, 33));
+    }
+}
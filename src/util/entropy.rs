@@ -0,0 +1,203 @@
+//! Sanity checks for raw entropy (e.g. a dump from a hardware RNG) before it's
+//! turned into a wallet seed. These can only catch obviously non-random input,
+//! they can't prove that the input is actually random.
+
+use openssl;
+use openssl::crypto::hash::HashType::SHA512;
+
+/// The minimum number of bytes of entropy we'll accept.
+pub static MIN_LENGTH: uint = 32;
+
+// The chi-square statistic of the byte frequencies above which we consider the
+// distribution too skewed. Random input gives values around 255 (the degrees
+// of freedom), and stays well under this even for 32 bytes.
+static MAX_CHI_SQUARE: f64 = 500.0;
+
+// The longest run of a repeated byte that we accept.
+static MAX_RUN: uint = 8;
+
+// The longest pattern we look for when checking if the input is a short
+// pattern repeated over and over.
+static MAX_PERIOD: uint = 64;
+
+/// The problems that the checks can find.
+#[deriving(Clone, PartialEq, Show)]
+pub enum Problem {
+    /// There are fewer than MIN_LENGTH bytes.
+    TooShort,
+    /// Every byte is the same.
+    AllSame,
+    /// A short pattern repeats over the whole input, or a byte repeats many
+    /// times in a row.
+    Repetitive,
+    /// Some byte values occur far more often than others (e.g. text).
+    SkewedDistribution
+}
+
+/// The results of analyzing some entropy.
+#[deriving(Clone, PartialEq, Show)]
+pub struct Report {
+    /// The number of bytes analyzed.
+    pub length: uint,
+    /// The number of distinct byte values.
+    pub distinct_bytes: uint,
+    /// The chi-square statistic of the byte frequencies against a uniform
+    /// distribution.
+    pub chi_square: f64,
+    /// The longest run of the same byte.
+    pub longest_run: uint,
+    /// The problems found, if any.
+    pub problems: Vec<Problem>
+}
+
+impl Report {
+    /// Whether the input passed all of the checks.
+    pub fn is_acceptable(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Runs all of the checks on the given entropy, and returns a Report.
+pub fn analyze(data: &[u8]) -> Report {
+    let mut counts = [0u, ..256];
+    for &byte in data.iter() {
+        counts[byte as uint] += 1;
+    }
+
+    let distinct_bytes = counts.iter().filter(|&&count| count > 0).count();
+    let chi_square = chi_square(counts.as_slice(), data.len());
+    let longest_run = longest_run(data);
+
+    let mut problems = Vec::new();
+    if data.len() < MIN_LENGTH {
+        problems.push(Problem::TooShort);
+    }
+    if distinct_bytes == 1 {
+        problems.push(Problem::AllSame);
+    } else if longest_run > MAX_RUN || is_periodic(data) {
+        problems.push(Problem::Repetitive);
+    }
+    if distinct_bytes > 1 && chi_square > MAX_CHI_SQUARE {
+        problems.push(Problem::SkewedDistribution);
+    }
+
+    Report {
+        length: data.len(),
+        distinct_bytes: distinct_bytes,
+        chi_square: chi_square,
+        longest_run: longest_run,
+        problems: problems
+    }
+}
+
+/// Turns entropy of any length into a 64-byte seed by hashing all of it with
+/// SHA-512, so that any extra entropy isn't wasted.
+pub fn seed_from_entropy(data: &[u8]) -> Vec<u8> {
+    openssl::crypto::hash::hash(SHA512, data)
+}
+
+// Computes the chi-square statistic of the given byte counts against a uniform
+// distribution.
+fn chi_square(counts: &[uint], total: uint) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+
+    let expected = total as f64 / 256.0;
+    counts.iter().fold(0.0, |sum, &count| {
+        let difference = count as f64 - expected;
+        sum + difference * difference / expected
+    })
+}
+
+// Finds the length of the longest run of the same byte.
+fn longest_run(data: &[u8]) -> uint {
+    let mut longest = 0;
+    let mut current = 0;
+    for (idx, byte) in data.iter().enumerate() {
+        if idx > 0 && data[idx - 1] == *byte {
+            current += 1;
+        } else {
+            current = 1;
+        }
+        if current > longest {
+            longest = current;
+        }
+    }
+    longest
+}
+
+// Checks whether the data is a pattern of up to MAX_PERIOD bytes repeated at
+// least twice.
+fn is_periodic(data: &[u8]) -> bool {
+    range(1, MAX_PERIOD + 1).any(|period| {
+        data.len() >= period * 2 &&
+        range(period, data.len()).all(|idx| data[idx] == data[idx - period])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serialize::hex::FromHex;
+
+    use super::{analyze, seed_from_entropy};
+    use super::Problem;
+
+    // 64 bytes from /dev/urandom.
+    static RANDOM: &'static str =
+        "4b1cdd9202ef9f55b97cdf4f31d9ad156c31746fa4f33503c4ab44ec3ab19698\
+         c919a4f3f8b5c9837f8766572ac1a541e910637817d07979b62c8eb8e86519fe";
+
+    #[test]
+    fn test_analyze_random() {
+        let data = RANDOM.from_hex().unwrap();
+        let report = analyze(data.as_slice());
+        assert!(report.is_acceptable());
+        assert_eq!(report.length, 64);
+        assert_eq!(report.distinct_bytes, 58);
+        assert_eq!(report.chi_square, 240.0);
+    }
+
+    #[test]
+    fn test_analyze_all_zero() {
+        let data = Vec::from_elem(64, 0u8);
+        let report = analyze(data.as_slice());
+        assert!(!report.is_acceptable());
+        assert_eq!(report.problems, vec![Problem::AllSame]);
+        assert_eq!(report.longest_run, 64);
+    }
+
+    #[test]
+    fn test_analyze_text() {
+        let data = b"The quick brown fox jumps over the lazy dog.";
+        let report = analyze(data.as_slice());
+        assert!(!report.is_acceptable());
+        assert_eq!(report.problems, vec![Problem::SkewedDistribution]);
+    }
+
+    #[test]
+    fn test_analyze_repeated_pattern() {
+        let data = RANDOM.from_hex().unwrap();
+        let mut repeated = data.slice_to(16).to_vec();
+        repeated.push_all(data.slice_to(16));
+        repeated.push_all(data.slice_to(16));
+        let report = analyze(repeated.as_slice());
+        assert!(report.problems.contains(&Problem::Repetitive));
+    }
+
+    #[test]
+    fn test_analyze_short() {
+        let data = RANDOM.from_hex().unwrap();
+        let report = analyze(data.slice_to(31));
+        assert_eq!(report.problems, vec![Problem::TooShort]);
+    }
+
+    #[test]
+    fn test_seed_from_entropy() {
+        let data = RANDOM.from_hex().unwrap();
+        let seed = seed_from_entropy(data.as_slice());
+        let expected = "f4082b395b3fa8411c7be8d590ab2bfc13182afc5c9f07f522f0cc7603053108\
+                        3ae942ee98e772870b24f019b9ec0aee1fe42b271bf0508fbbd0154e15aea2c1";
+        assert_eq!(seed, expected.from_hex().unwrap());
+    }
+}
@@ -0,0 +1,200 @@
+//! Fixed-length hash types, so that hashes can't be mixed up with other raw
+//! byte vectors.
+
+use openssl;
+use openssl::crypto::hash::HashType::{SHA256, RIPEMD160};
+use serialize::hex::{ToHex, FromHex};
+
+use std::fmt;
+use std::str::FromStr;
+
+use util::capabilities;
+use util::capabilities::Ripemd160Impl;
+use util::ripemd160;
+
+/// Length of a raw Hash160.
+pub static HASH160_LENGTH: uint = 20;
+
+/// Length of a raw Sha256Hash.
+pub static SHA256_LENGTH: uint = 32;
+
+/// Represents a 20-byte RIPEMD-160 hash of a SHA-256 hash, which is what
+/// Bitcoin addresses are made of.
+#[deriving(Clone, PartialEq)]
+pub struct Hash160(Vec<u8>);
+
+impl Hash160 {
+    /// Computes the Hash160 of the given data, i.e. RIPEMD160(SHA256(data)).
+    /// Falls back to the built-in RIPEMD-160 if OpenSSL doesn't provide it,
+    /// which is only checked once per run.
+    pub fn from_data(data: &[u8]) -> Hash160 {
+        Hash160::from_data_with(data, capabilities::ripemd160_impl())
+    }
+
+    /// Same as from_data(), but with the RIPEMD-160 implementation to use
+    /// given explicitly.
+    pub fn from_data_with(data: &[u8], implementation: Ripemd160Impl) -> Hash160 {
+        let sha = openssl::crypto::hash::hash(SHA256, data);
+        let ripemd = match implementation {
+            Ripemd160Impl::OpenSsl => openssl::crypto::hash::hash(RIPEMD160, sha.as_slice()),
+            Ripemd160Impl::BuiltIn => ripemd160::hash(sha.as_slice())
+        };
+        Hash160(ripemd)
+    }
+
+    /// Creates a Hash160 from an already computed 20-byte hash. Returns None
+    /// if the data is the wrong length.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Hash160> {
+        if bytes.len() == HASH160_LENGTH {
+            Some(Hash160(bytes.to_vec()))
+        } else {
+            None
+        }
+    }
+
+    /// Gets the raw hash as a slice of bytes.
+    pub fn get_data(&self) -> &[u8] {
+        let Hash160(ref data) = *self;
+        data.as_slice()
+    }
+}
+
+// Hashes are displayed as lowercase hex.
+impl fmt::Show for Hash160 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.get_data().to_hex())
+    }
+}
+
+// Parses a Hash160 from 40 hex digits.
+impl FromStr for Hash160 {
+    fn from_str(s: &str) -> Option<Hash160> {
+        match s.from_hex() {
+            Ok(data) => Hash160::from_bytes(data.as_slice()),
+            Err(_) => None
+        }
+    }
+}
+
+/// Represents a 32-byte SHA-256 hash.
+#[deriving(Clone, PartialEq)]
+pub struct Sha256Hash(Vec<u8>);
+
+impl Sha256Hash {
+    /// Computes the SHA-256 hash of the given data.
+    pub fn from_data(data: &[u8]) -> Sha256Hash {
+        Sha256Hash(openssl::crypto::hash::hash(SHA256, data))
+    }
+
+    /// Creates a Sha256Hash from an already computed 32-byte hash. Returns
+    /// None if the data is the wrong length.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Sha256Hash> {
+        if bytes.len() == SHA256_LENGTH {
+            Some(Sha256Hash(bytes.to_vec()))
+        } else {
+            None
+        }
+    }
+
+    /// Gets the raw hash as a slice of bytes.
+    pub fn get_data(&self) -> &[u8] {
+        let Sha256Hash(ref data) = *self;
+        data.as_slice()
+    }
+}
+
+// Hashes are displayed as lowercase hex.
+impl fmt::Show for Sha256Hash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.get_data().to_hex())
+    }
+}
+
+// Parses a Sha256Hash from 64 hex digits.
+impl FromStr for Sha256Hash {
+    fn from_str(s: &str) -> Option<Sha256Hash> {
+        match s.from_hex() {
+            Ok(data) => Sha256Hash::from_bytes(data.as_slice()),
+            Err(_) => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serialize::hex::FromHex;
+
+    use std::str::FromStr;
+
+    use util::base58;
+    use util::capabilities::Ripemd160Impl;
+
+    use super::{Hash160, Sha256Hash};
+
+    #[test]
+    fn test_from_data() {
+        let hash = Hash160::from_data(b"".as_slice());
+        let expected = "B472A266D0BD89C13706A4132CCFB16F7C3B9FCB".from_hex().unwrap();
+        assert_eq!(hash.get_data(), expected.as_slice());
+    }
+
+    #[test]
+    fn test_from_data_built_in() {
+        // Address vectors should come out the same with the built-in
+        // RIPEMD-160.
+        let public_key = "04EB4EA815229359CEC3965507FF68F8B3C7B8632FF9ABD46A06520A838C468AFC\
+                            5B2EB3588549E626200A698D38966B38498EB27CBAAADE5EEE6DEF01DF061F73";
+        let public_key = public_key.from_hex().unwrap();
+        let address = base58::decode("1BN7qZoGjmpwD3nSLrFy6xfdDQbTvQDUbs").unwrap();
+
+        let hash = Hash160::from_data_with(public_key.as_slice(), Ripemd160Impl::BuiltIn);
+        assert_eq!(hash.get_data(), address.slice(1, 21));
+        assert_eq!(hash, Hash160::from_data_with(public_key.as_slice(), Ripemd160Impl::OpenSsl));
+    }
+
+    #[test]
+    fn test_from_bytes_invalid_length() {
+        let data = "B472A266D0BD89C13706A4132CCFB16F7C3B9F".from_hex().unwrap();
+        assert!(Hash160::from_bytes(data.as_slice()).is_none());
+    }
+
+    #[test]
+    fn test_to_string_from_str() {
+        let hash = Hash160::from_data(b"abc".as_slice());
+        let string = hash.to_string();
+        assert_eq!(string.as_slice(), "bb1be98c142444d7a56aa3981c3942a978e4dc33");
+
+        let parsed: Option<Hash160> = FromStr::from_str(string.as_slice());
+        assert_eq!(parsed, Some(hash));
+    }
+
+    #[test]
+    fn test_from_str_invalid() {
+        let parsed: Option<Hash160> = FromStr::from_str("not hex");
+        assert!(parsed.is_none());
+
+        let parsed: Option<Hash160> = FromStr::from_str("bb1be98c");
+        assert!(parsed.is_none());
+    }
+
+    #[test]
+    fn test_sha256_from_data() {
+        let hash = Sha256Hash::from_data(b"".as_slice());
+        assert_eq!(hash.to_string().as_slice(),
+                   "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    }
+
+    #[test]
+    fn test_sha256_from_bytes_invalid_length() {
+        let data = "E3B0C44298FC1C149AFBF4C8996FB924".from_hex().unwrap();
+        assert!(Sha256Hash::from_bytes(data.as_slice()).is_none());
+    }
+
+    #[test]
+    fn test_sha256_to_string_from_str() {
+        let hash = Sha256Hash::from_data(b"abc".as_slice());
+        let string = hash.to_string();
+        let parsed: Option<Sha256Hash> = FromStr::from_str(string.as_slice());
+        assert_eq!(parsed, Some(hash));
+    }
+}
@@ -3,4 +3,10 @@
 pub mod ecdsa;
 pub mod base58;
 pub mod wif;
-
+pub mod hash;
+pub mod ripemd160;
+pub mod capabilities;
+pub mod entropy;
+pub mod varint;
+pub mod varstring;
+pub mod siphash;
@@ -0,0 +1,158 @@
+//! Pure Rust implementation of the RIPEMD-160 hash function, used as a
+//! fallback for builds of OpenSSL that have RIPEMD-160 disabled.
+
+// Which word of the message block each step of the left line reads.
+static R_LEFT: [uint, ..80] = [
+     0,  1,  2,  3,  4,  5,  6,  7,  8,  9, 10, 11, 12, 13, 14, 15,
+     7,  4, 13,  1, 10,  6, 15,  3, 12,  0,  9,  5,  2, 14, 11,  8,
+     3, 10, 14,  4,  9, 15,  8,  1,  2,  7,  0,  6, 13, 11,  5, 12,
+     1,  9, 11, 10,  0,  8, 12,  4, 13,  3,  7, 15, 14,  5,  6,  2,
+     4,  0,  5,  9,  7, 12,  2, 10, 14,  1,  3,  8, 11,  6, 15, 13
+];
+
+// Which word of the message block each step of the right line reads.
+static R_RIGHT: [uint, ..80] = [
+     5, 14,  7,  0,  9,  2, 11,  4, 13,  6, 15,  8,  1, 10,  3, 12,
+     6, 11,  3,  7,  0, 13,  5, 10, 14, 15,  8, 12,  4,  9,  1,  2,
+    15,  5,  1,  3,  7, 14,  6,  9, 11,  8, 12,  2, 10,  0,  4, 13,
+     8,  6,  4,  1,  3, 11, 15,  0,  5, 12,  2, 13,  9,  7, 10, 14,
+    12, 15, 10,  4,  1,  5,  8,  7,  6,  2, 13, 14,  0,  3,  9, 11
+];
+
+// How far each step of the left line rotates.
+static S_LEFT: [uint, ..80] = [
+    11, 14, 15, 12,  5,  8,  7,  9, 11, 13, 14, 15,  6,  7,  9,  8,
+     7,  6,  8, 13, 11,  9,  7, 15,  7, 12, 15,  9, 11,  7, 13, 12,
+    11, 13,  6,  7, 14,  9, 13, 15, 14,  8, 13,  6,  5, 12,  7,  5,
+    11, 12, 14, 15, 14, 15,  9,  8,  9, 14,  5,  6,  8,  6,  5, 12,
+     9, 15,  5, 11,  6,  8, 13, 12,  5, 12, 13, 14, 11,  8,  5,  6
+];
+
+// How far each step of the right line rotates.
+static S_RIGHT: [uint, ..80] = [
+     8,  9,  9, 11, 13, 15, 15,  5,  7,  7,  8, 11, 14, 14, 12,  6,
+     9, 13, 15,  7, 12,  8,  9, 11,  7,  7, 12,  7,  6, 15, 13, 11,
+     9,  7, 15, 11,  8,  6,  6, 14, 12, 13,  5, 14, 13, 13,  7,  5,
+    15,  5,  8, 11, 14, 14,  6, 14,  6,  9, 12,  9, 12,  5, 15,  8,
+     8,  5, 12,  9, 12,  5, 14,  6,  8, 13,  6,  5, 15, 13, 11, 11
+];
+
+// The constant added in each round of the left line.
+static K_LEFT: [u32, ..5] = [0x00000000, 0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xa953fd4e];
+
+// The constant added in each round of the right line.
+static K_RIGHT: [u32, ..5] = [0x50a28be6, 0x5c4dd124, 0x6d703ef3, 0x7a6d76e9, 0x00000000];
+
+// The initial hash state.
+static INITIAL_STATE: [u32, ..5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+/// Hashes the given data with RIPEMD-160, returning the 20-byte hash.
+pub fn hash(data: &[u8]) -> Vec<u8> {
+    let mut state = INITIAL_STATE;
+
+    // Pad the message with a one bit, then zeroes until there are 8 bytes
+    // left in the last block, and then the message length in bits as a
+    // little-endian 64-bit number.
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0x00);
+    }
+    let bit_length = (data.len() as u64) * 8;
+    for i in range(0u, 8) {
+        message.push((bit_length >> (i * 8)) as u8);
+    }
+
+    for block in message.as_slice().chunks(64) {
+        compress(&mut state, block);
+    }
+
+    let mut result = Vec::with_capacity(20);
+    for word in state.iter() {
+        for i in range(0u, 4) {
+            result.push((*word >> (i * 8)) as u8);
+        }
+    }
+
+    result
+}
+
+// Runs the compression function on a single 64-byte block, updating the hash
+// state.
+fn compress(state: &mut [u32, ..5], block: &[u8]) {
+    let mut x = [0u32, ..16];
+    for i in range(0u, 16) {
+        x[i] = (block[i * 4] as u32) |
+               (block[i * 4 + 1] as u32 << 8) |
+               (block[i * 4 + 2] as u32 << 16) |
+               (block[i * 4 + 3] as u32 << 24);
+    }
+
+    let (mut al, mut bl, mut cl, mut dl, mut el) = (state[0], state[1], state[2], state[3], state[4]);
+    let (mut ar, mut br, mut cr, mut dr, mut er) = (state[0], state[1], state[2], state[3], state[4]);
+
+    for j in range(0u, 80) {
+        let t = rotate_left(al + f(j, bl, cl, dl) + x[R_LEFT[j]] + K_LEFT[j / 16], S_LEFT[j]) + el;
+        al = el;
+        el = dl;
+        dl = rotate_left(cl, 10);
+        cl = bl;
+        bl = t;
+
+        let t = rotate_left(ar + f(79 - j, br, cr, dr) + x[R_RIGHT[j]] + K_RIGHT[j / 16], S_RIGHT[j]) + er;
+        ar = er;
+        er = dr;
+        dr = rotate_left(cr, 10);
+        cr = br;
+        br = t;
+    }
+
+    let t = state[1] + cl + dr;
+    state[1] = state[2] + dl + er;
+    state[2] = state[3] + el + ar;
+    state[3] = state[4] + al + br;
+    state[4] = state[0] + bl + cr;
+    state[0] = t;
+}
+
+// The nonlinear function used in each step, which depends on the round.
+fn f(j: uint, x: u32, y: u32, z: u32) -> u32 {
+    match j / 16 {
+        0 => x ^ y ^ z,
+        1 => (x & y) | (!x & z),
+        2 => (x | !y) ^ z,
+        3 => (x & z) | (y & !z),
+        _ => x ^ (y | !z)
+    }
+}
+
+// Rotates a 32-bit word left by n bits, where 0 < n < 32.
+fn rotate_left(x: u32, n: uint) -> u32 {
+    (x << n) | (x >> (32 - n))
+}
+
+#[cfg(test)]
+mod tests {
+    use serialize::hex::FromHex;
+
+    use super::hash;
+
+    #[test]
+    fn test_hash() {
+        let expected = "9C1185A5C5E9FC54612808977EE8F548B2258D31".from_hex().unwrap();
+        assert_eq!(hash(b"".as_slice()), expected);
+
+        let expected = "8EB208F7E05D987A9B044A8E98C6B087F15A0BFC".from_hex().unwrap();
+        assert_eq!(hash(b"abc".as_slice()), expected);
+
+        let expected = "5D0689EF49D2FAE572B881B123A85FFA21595F36".from_hex().unwrap();
+        assert_eq!(hash(b"message digest".as_slice()), expected);
+    }
+
+    #[test]
+    fn test_hash_multiple_blocks() {
+        let data = Vec::from_elem(1000, b'a');
+        let expected = "AA69DEEE9A8922E92F8105E007F76110F381E9CF".from_hex().unwrap();
+        assert_eq!(hash(data.as_slice()), expected);
+    }
+}
@@ -0,0 +1,103 @@
+//! Pure Rust implementation of the SipHash-2-4 keyed hash function, which
+//! BIP 158 uses to hash items into compact block filters.
+
+/// Hashes the given data with SipHash-2-4, using the 128-bit key made up of
+/// `k0` and `k1` (each read from key bytes in little-endian order).
+pub fn hash(k0: u64, k1: u64, data: &[u8]) -> u64 {
+    let mut state = State {
+        v0: k0 ^ 0x736f6d6570736575,
+        v1: k1 ^ 0x646f72616e646f6d,
+        v2: k0 ^ 0x6c7967656e657261,
+        v3: k1 ^ 0x7465646279746573
+    };
+
+    // Process the data in 8-byte little-endian words, leaving off the last
+    // partial word (which may be empty).
+    let full_words = data.len() / 8;
+    for word in data.chunks(8).take(full_words) {
+        state.compress(read_le(word));
+    }
+
+    // The last word is the leftover bytes, with the low byte of the data's
+    // length in its top byte.
+    let last_word = read_le(data.slice_from(full_words * 8)) | ((data.len() as u64 & 0xff) << 56);
+    state.compress(last_word);
+
+    state.v2 ^= 0xff;
+    for _ in range(0u, 4) {
+        state.round();
+    }
+
+    state.v0 ^ state.v1 ^ state.v2 ^ state.v3
+}
+
+// The internal state of SipHash.
+struct State {
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64
+}
+
+impl State {
+    // Mixes one 8-byte word of the message into the state, with two rounds.
+    fn compress(&mut self, word: u64) {
+        self.v3 ^= word;
+        self.round();
+        self.round();
+        self.v0 ^= word;
+    }
+
+    // A single SipRound.
+    fn round(&mut self) {
+        self.v0 += self.v1;
+        self.v1 = rotate_left(self.v1, 13);
+        self.v1 ^= self.v0;
+        self.v0 = rotate_left(self.v0, 32);
+
+        self.v2 += self.v3;
+        self.v3 = rotate_left(self.v3, 16);
+        self.v3 ^= self.v2;
+
+        self.v0 += self.v3;
+        self.v3 = rotate_left(self.v3, 21);
+        self.v3 ^= self.v0;
+
+        self.v2 += self.v1;
+        self.v1 = rotate_left(self.v1, 17);
+        self.v1 ^= self.v2;
+        self.v2 = rotate_left(self.v2, 32);
+    }
+}
+
+// Reads up to 8 bytes as a little-endian number.
+fn read_le(bytes: &[u8]) -> u64 {
+    bytes.iter().enumerate().fold(0, |n, (i, &byte)| n | (byte as u64 << (i * 8)))
+}
+
+// Rotates a 64-bit word left by n bits, where 0 < n < 64.
+fn rotate_left(x: u64, n: uint) -> u64 {
+    (x << n) | (x >> (64 - n))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hash;
+
+    // The key from the reference test vectors: the bytes 00 01 02 ... 0f.
+    static K0: u64 = 0x0706050403020100;
+    static K1: u64 = 0x0f0e0d0c0b0a0908;
+
+    #[test]
+    fn test_hash() {
+        // From the SipHash paper's reference implementation, where the message
+        // is the bytes 00 01 02 ... of each length.
+        let message: Vec<u8> = range(0u8, 15).collect();
+        assert_eq!(hash(K0, K1, message.slice_to(0)), 0x726fdb47dd0e0e31);
+        assert_eq!(hash(K0, K1, message.slice_to(1)), 0x74f839c593dc67fd);
+        assert_eq!(hash(K0, K1, message.slice_to(7)), 0xab0200f58b01d137);
+        assert_eq!(hash(K0, K1, message.slice_to(8)), 0x93f5f5799a932462);
+        assert_eq!(hash(K0, K1, message.slice_to(9)), 0x9e0082df0ba9e4b0);
+        assert_eq!(hash(K0, K1, message.as_slice()), 0xa129ca6149be45e5);
+    }
+}
@@ -0,0 +1,86 @@
+//! Bitcoin's variable-length integer encoding (also known as "compact size"),
+//! used for lengths and counts in transactions and P2P messages.
+
+/// Encode an integer in as few bytes as possible. Values below 0xfd take a
+/// single byte; larger values take a marker byte (0xfd, 0xfe or 0xff) followed
+/// by the value as a little-endian 16, 32 or 64-bit integer.
+pub fn encode(n: u64) -> Vec<u8> {
+    if n < 0xfd {
+        return vec![n as u8];
+    }
+
+    let (marker, length) = if n <= 0xffff {
+        (0xfd, 2)
+    } else if n <= 0xffffffff {
+        (0xfe, 4)
+    } else {
+        (0xff, 8)
+    };
+
+    let mut result = Vec::with_capacity(1 + length);
+    result.push(marker);
+    for i in range(0u, length) {
+        result.push((n >> (i * 8)) as u8);
+    }
+
+    result
+}
+
+/// Decode an integer from the start of the given bytes. Returns the integer
+/// and the number of bytes it took up, or None if there aren't enough bytes.
+pub fn decode(bytes: &[u8]) -> Option<(u64, uint)> {
+    if bytes.is_empty() {
+        return None;
+    }
+
+    let length = match bytes[0] {
+        0xfd => 2,
+        0xfe => 4,
+        0xff => 8,
+        n => return Some((n as u64, 1))
+    };
+
+    if bytes.len() < 1 + length {
+        return None;
+    }
+
+    let n = range(0u, length).fold(0u64, |n, i| n | (bytes[1 + i] as u64 << (i * 8)));
+    Some((n, 1 + length))
+}
+
+#[cfg(test)]
+mod tests {
+    use serialize::hex::FromHex;
+
+    use super::{encode, decode};
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode(0), "00".from_hex().unwrap());
+        assert_eq!(encode(0xfc), "FC".from_hex().unwrap());
+        assert_eq!(encode(0xfd), "FDFD00".from_hex().unwrap());
+        assert_eq!(encode(0xffff), "FDFFFF".from_hex().unwrap());
+        assert_eq!(encode(0x10000), "FE00000100".from_hex().unwrap());
+        assert_eq!(encode(0x100000000), "FF0000000001000000".from_hex().unwrap());
+    }
+
+    #[test]
+    fn test_decode() {
+        let bytes = "FDFD00AB".from_hex().unwrap();
+        assert_eq!(decode(bytes.as_slice()), Some((0xfd, 3)));
+
+        let bytes = "FF0000000001000000".from_hex().unwrap();
+        assert_eq!(decode(bytes.as_slice()), Some((0x100000000, 9)));
+
+        let bytes = "FC".from_hex().unwrap();
+        assert_eq!(decode(bytes.as_slice()), Some((0xfc, 1)));
+    }
+
+    #[test]
+    fn test_decode_too_short() {
+        assert_eq!(decode(&[]), None);
+
+        let bytes = "FE000001".from_hex().unwrap();
+        assert_eq!(decode(bytes.as_slice()), None);
+    }
+}
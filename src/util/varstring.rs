@@ -0,0 +1,71 @@
+//! Variable-length strings, as used in P2P messages: a variable-length integer
+//! giving the number of bytes, followed by that many bytes of UTF-8.
+
+use util::varint;
+
+/// Encode a string as a var-string.
+pub fn encode(s: &str) -> Vec<u8> {
+    let mut result = varint::encode(s.len() as u64);
+    result.push_all(s.as_bytes());
+    result
+}
+
+/// Decode a var-string from the start of the given bytes. Returns the string
+/// and the number of bytes it took up, or None if there aren't enough bytes or
+/// the string isn't valid UTF-8.
+pub fn decode(bytes: &[u8]) -> Option<(String, uint)> {
+    let (length, prefix_length) = match varint::decode(bytes) {
+        Some(decoded) => decoded,
+        None => return None
+    };
+
+    if length > (bytes.len() - prefix_length) as u64 {
+        return None;
+    }
+
+    let end = prefix_length + length as uint;
+    match String::from_utf8(bytes.slice(prefix_length, end).to_vec()) {
+        Ok(s) => Some((s, end)),
+        Err(_) => None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serialize::hex::FromHex;
+
+    use super::{encode, decode};
+
+    #[test]
+    fn test_empty_string() {
+        let bytes = encode("");
+        assert_eq!(bytes, vec![0x00]);
+        assert_eq!(decode(bytes.as_slice()), Some(("".to_string(), 1)));
+    }
+
+    #[test]
+    fn test_ascii_string() {
+        let bytes = encode("/Satoshi:0.7.2/");
+        assert_eq!(bytes, "0F2F5361746F7368693A302E372E322F".from_hex().unwrap());
+        assert_eq!(decode(bytes.as_slice()), Some(("/Satoshi:0.7.2/".to_string(), 16)));
+    }
+
+    #[test]
+    fn test_utf8_string() {
+        // Three characters, but six bytes.
+        let bytes = encode("¢€a");
+        assert_eq!(bytes, "06C2A2E282AC61".from_hex().unwrap());
+        assert_eq!(decode(bytes.as_slice()), Some(("¢€a".to_string(), 7)));
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        // Too short for the stated length.
+        let bytes = "052F536174".from_hex().unwrap();
+        assert_eq!(decode(bytes.as_slice()), None);
+
+        // Not valid UTF-8.
+        let bytes = "02C328".from_hex().unwrap();
+        assert_eq!(decode(bytes.as_slice()), None);
+    }
+}
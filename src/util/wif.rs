@@ -33,22 +33,26 @@ pub fn decode(data: &[u8], version_byte: u8) -> Option<Vec<u8>> {
     }
 }
 
-/// Checks whether the given data satisfies the checksum. (Assumes the last four
-/// bytes of the slice is the checksum.)
+/// Checks whether the given data satisfies the checksum, by splitting off the
+/// last four bytes and comparing them with the checksum of the rest. Returns
+/// false if the data is too short to even contain a checksum.
 pub fn check(data: &[u8]) -> bool {
-    assert!(data.len() > CHECKSUM_LENGTH);
+    if data.len() < CHECKSUM_LENGTH {
+        return false;
+    }
     let (payload, given_checksum) = data.split_at(data.len() - CHECKSUM_LENGTH);
     checksum(payload).as_slice() == given_checksum
 }
 
-// Computes a checksum of the given data.
-fn checksum(data: &[u8]) -> Vec<u8> {
+/// Computes the 4-byte checksum of the given data, which is the first four
+/// bytes of its double SHA-256 hash.
+pub fn checksum(data: &[u8]) -> Vec<u8> {
     let double_hash = double_sha256(data);
     double_hash.slice(0, 4).to_vec()
 }
 
-// Performs a double SHA256 hash of the given data.
-fn double_sha256(data: &[u8]) -> Vec<u8> {
+/// Performs a double SHA256 hash of the given data.
+pub fn double_sha256(data: &[u8]) -> Vec<u8> {
     let first_hash = openssl::crypto::hash::hash(SHA256, data);
     openssl::crypto::hash::hash(SHA256, first_hash.as_slice())
 }
@@ -110,6 +114,19 @@ mod tests {
 
         let data = "10010966776006953D5567439E5E39F86A0D273BEED61967F6".from_hex().unwrap();
         assert!(!check(data.as_slice()));
+
+        let data = "00010966776006953D5567439E5E39F86A0D273BEED61967F7".from_hex().unwrap();
+        assert!(!check(data.as_slice()));
+    }
+
+    #[test]
+    fn test_check_short() {
+        // Just a checksum, of an empty payload.
+        assert!(check(&[0x5D, 0xF6, 0xE0, 0xE2]));
+        assert!(!check(&[0x5D, 0xF6, 0xE0, 0xE3]));
+
+        assert!(!check(&[0x5D, 0xF6, 0xE0]));
+        assert!(!check(&[]));
     }
 
     #[test]
@@ -119,6 +136,14 @@ mod tests {
         assert_eq!(checksum(data.as_slice()), expected);
     }
 
+    #[test]
+    fn test_checksum_empty() {
+        // The checksum of nothing is still well-defined: it's the first four
+        // bytes of the double-SHA256 of the empty string.
+        let expected = "5DF6E0E2".from_hex().unwrap();
+        assert_eq!(checksum(&[]), expected);
+    }
+
     #[test]
     fn test_double_sha256() {
         let data = "00010966776006953D5567439E5E39F86A0D273BEE".from_hex().unwrap();
@@ -9,6 +9,7 @@ use std::io::{File, BufferedReader, IoResult, IoError, OtherIoError};
 use std::io::fs::PathExtensions;
 
 use util::base58;
+use util::capabilities;
 use protocol::address::Address;
 use protocol::private_key::PrivateKey;
 use protocol::private_key;
@@ -58,6 +59,8 @@ impl Wallet {
     /// failure, and specifically an OtherIoError if the contents of the file
     /// are invalid.
     pub fn load(path: &Path) -> IoResult<Wallet> {
+        try!(check_encryption_support());
+
         let file = try!(File::open(path));
         let mut reader = BufferedReader::new(file);
         let parsed = try!(wallet_parser::parse(&mut reader));
@@ -175,6 +178,8 @@ impl Wallet {
         // TODO: make a backup copy first, to delete when the new file is
         // closed.
 
+        try!(check_encryption_support());
+
         let (salt, iv, encrypted_data) = self.encrypt();
 
         let mut file = try!(File::create(&self.path));
@@ -282,3 +287,16 @@ impl Wallet {
     }
 }
 
+// Makes sure the linked OpenSSL library supports the algorithms used to encrypt
+// the private keys, so that we fail with a clear error instead of writing or
+// reading garbage.
+fn check_encryption_support() -> IoResult<()> {
+    match capabilities::missing_encryption_algorithm() {
+        Some(algorithm) => Err(IoError {
+            kind: OtherIoError,
+            desc: "encryption algorithm unavailable",
+            detail: Some(format!("This build of OpenSSL lacks {}; wallet encryption is unavailable", algorithm))
+        }),
+        None => Ok(())
+    }
+}